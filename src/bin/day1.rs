@@ -1,56 +1,37 @@
-use std::borrow::Cow;
-use std::env;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use aoc2019::cli::Args;
+use aoc2019::fuel::{self, ModuleFuel};
 
-fn main() -> std::io::Result<()> {
-    let reader = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        BufReader::new(File::open(name.as_ref())?)
-    };
+use std::fs;
+use std::io;
 
-    let result: (u32, u32) = reader.lines()
-        .filter_map(|s| s.ok())
-        .filter_map(|s| s.parse().ok())
-        .map(|n| (compute_fuel(n), compute_fuel_fuel(n)))
-        .fold((0, 0), |acc, x| (acc.0 + x.0, acc.1 + x.1));
+fn main() -> io::Result<()> {
+    let args = Args::from_env();
+    let input = fs::read_to_string(args.input())?;
 
-    println!("{:?}", result);
-
-    Ok(())
-}
-
-fn compute_fuel(input: u32) -> u32 {
-    if input > 2 * 3 {
-        input / 3 - 2
+    // Masses too big for u64 need --wide
+    if args.flag("wide").is_some() {
+        run::<u128>(&input, &args)
     } else {
-        0
+        run::<u64>(&input, &args)
     }
 }
 
-#[test]
-fn test_compute_fuel() {
-    assert_eq!(compute_fuel(0), 0);
-    assert_eq!(compute_fuel(12), 2);
-    assert_eq!(compute_fuel(14), 2);
-    assert_eq!(compute_fuel(1969), 654);
-    assert_eq!(compute_fuel(100756), 33583);
-}
-
-fn compute_fuel_fuel(input: u32) -> u32 {
-    let mut total = 0;
-    let mut fuel = compute_fuel(input);
-    while fuel > 0 {
-        total += fuel;
-        fuel = compute_fuel(fuel);
+fn run<T: fuel::Mass + Into<u128> + Send + Sync>(input: &str, args: &Args) -> io::Result<()> {
+    let masses = fuel::parse_masses::<T>(input)?;
+
+    // Break the fuel down per module, e.g. --report or --report=csv
+    if let Some(format) = args.flag("report") {
+        let modules = masses.iter().map(|&mass| ModuleFuel::new(mass)).collect::<Vec<ModuleFuel<T>>>();
+        match format {
+            "" | "table" => print!("{}", fuel::format_table(&modules)?),
+            "csv" => print!("{}", fuel::format_csv(&modules)),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown report format {}", format))),
+        }
     }
-    total
-}
 
-#[test]
-fn test_compute_fuel_fuel() {
-    assert_eq!(compute_fuel_fuel(14), 2);
-    assert_eq!(compute_fuel_fuel(1969), 966);
-    assert_eq!(compute_fuel_fuel(100756), 50346);
+    let (fuel, total) = fuel::batch_totals(&masses)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Total fuel overflows u128"))?;
+    println!("Part 1: {}", fuel);
+    println!("Part 2: {}", total);
+    Ok(())
 }
@@ -1,13 +1,18 @@
 #[macro_use]
 extern crate quick_error;
 
-use ordered_float::NotNan;
+use rayon::prelude::*;
 
-use std::{env, fs, io};
-use std::borrow::Cow;
-use std::collections::BTreeMap;
-use std::f32::consts::PI;
+use aoc2019::cli::Args;
+use aoc2019::grid::{Grid, Point};
+use aoc2019::raster::{self, Bitmap, Color, RenderOptions};
+use aoc2019::rng::Xorshift;
+
+use std::{fs, io};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::num::ParseIntError;
+use std::time::Instant;
 
 quick_error! {
     #[derive(Debug)]
@@ -20,50 +25,258 @@ quick_error! {
 type SuperResult<T> = Result<T, SuperError>;
 
 fn main() -> SuperResult<()> {
-    let input = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        fs::read_to_string(name.as_ref())?
+    let args = Args::from_env();
+
+    // Time visibility on a generated map instead, e.g. --bench=200 --density=50
+    if let Some(size) = args.flag("bench") {
+        let size = if size.is_empty() { 200 } else { size.parse()? };
+        let density = args.flag("density").map(str::parse).transpose()?.unwrap_or(50);
+        return bench_visibility(size, density);
+    }
+
+    let input = fs::read_to_string(args.input())?;
+
+    let map = parse_asteroid_map(&input)?;
+    let coords = &map.asteroids;
+    let visible = compute_visibility(coords);
+    let best = visible.iter().max().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "No asteroids on the map"))?;
+    println!("Part 1: {}", best);
+
+    // The station defaults to the one marked on the map, then to the best location from part 1
+    let station = match (args.flag("station"), map.station) {
+        (Some(station), _) => parse_point(station)?,
+        (None, Some(station)) => station,
+        (None, None) => coords[visible.iter().enumerate().max_by_key(|&(_, e)| e).unwrap().0],
     };
+    // Print the map with the chosen station marked, which can be saved and read back in
+    if args.flag("mark").is_some() {
+        println!("{}", AsteroidMap {station: Some(station), ..map.clone()}.to_text());
+    }
 
-    let coords = parse_asteroid_map(&input);
-    let visible = compute_visibility(&coords);
-    println!("Part 1: {}", visible.iter().max().unwrap());
+    let sweep = Sweep {
+        start: args.flag("start").map(parse_point).transpose()?.unwrap_or(Sweep::default().start),
+        rotation: if args.flag("counterclockwise").is_some() { Rotation::Counterclockwise } else { Rotation::Clockwise },
+    };
+    if sweep.start == Point::ORIGIN {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Start direction must not be 0,0").into());
+    }
+
+    let n = args.flag("nth").map(str::parse).transpose()?.unwrap_or(200);
+    match nth_vaporized(coords, station, sweep, n) {
+        Some(nth) => println!("Part 2: {}", nth.x * 100 + nth.y),
+        None => println!("Part 2: fewer than {} asteroids to vaporize", n),
+    }
 
-    let i = visible.iter().enumerate().max_by_key(|&(_, e)| e).unwrap().0;
-    let angles = sort_by_angle(&coords, i);
-    let removed = remove_by_angle(angles);
+    if let Some(target) = args.flag("target") {
+        let target = parse_point(target)?;
+        match rotation_of(coords, station, target) {
+            Some(rotation) => println!("{},{} is vaporized in rotation {}", target.x, target.y, rotation),
+            None => println!("{},{} is not an asteroid the laser can hit", target.x, target.y),
+        }
+    }
 
-    let two_hundred = removed[199];
-    println!("Part 2: {}", two_hundred.0 * 100 + two_hundred.1);
+    // Color asteroids by how many others they see, e.g. --heatmap for the terminal or --heatmap=map.ppm
+    if let Some(path) = args.flag("heatmap") {
+        let bitmap = heatmap(&map, &visible, station);
+        if path.is_empty() {
+            println!("{}", to_ansi(&bitmap, &heat_palette()));
+        } else {
+            let mut options = RenderOptions::parse(args.flag("scale"), None)?;
+            options.palette = heat_palette();
+            raster::save(path, &bitmap, &options)?;
+        }
+    }
+
+    // Number asteroids in the order the laser hits them, e.g. --order=20 for only the first 20
+    if let Some(count) = args.flag("order") {
+        let count = if count.is_empty() { coords.len() } else { count.parse()? };
+        println!("{}", label_vaporization(&map, station, sweep, count));
+    }
 
     Ok(())
 }
 
-type AngleMap = BTreeMap<NotNan<f32>, BTreeMap<NotNan<f32>, (usize, usize)>>;
+// Coordinates given on the command line as x,y
+fn parse_point(s: &str) -> SuperResult<Point> {
+    let (x, y) = s.split_once(',').ok_or_else(||
+        io::Error::new(io::ErrorKind::InvalidInput, format!("Expected x,y but got {:?}", s)))?;
+    Ok(Point::new(x.trim().parse()?, y.trim().parse()?))
+}
+
+#[test]
+fn test_parse_point() {
+    assert_eq!(parse_point("11,13").unwrap(), Point::new(11, 13));
+    assert_eq!(parse_point("0, -1").unwrap(), Point::new(0, -1));
+    assert!(parse_point("11").is_err());
+    assert!(parse_point("a,1").is_err());
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Rotation {
+    Clockwise,
+    Counterclockwise,
+}
+
+// Order in which the laser passes over directions: starting at a direction and turning one way
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Sweep {
+    start: Point,
+    rotation: Rotation,
+}
+
+impl Default for Sweep {
+    // The laser starts by pointing up and rotates clockwise
+    fn default() -> Self {
+        Sweep {start: Point::new(0, -1), rotation: Rotation::Clockwise}
+    }
+}
+
+impl Sweep {
+    // Positive when b is ahead of a by less than half a turn (with y pointing down)
+    fn cross(self, a: Point, b: Point) -> isize {
+        let cross = a.x * b.y - a.y * b.x;
+        match self.rotation {
+            Rotation::Clockwise => cross,
+            Rotation::Counterclockwise => -cross,
+        }
+    }
+
+    // The half turn starting at the start direction comes before the other half
+    fn half(self, d: Point) -> u8 {
+        let cross = self.cross(self.start, d);
+        let dot = self.start.x * d.x + self.start.y * d.y;
+        if cross > 0 || (cross == 0 && dot > 0) { 0 } else { 1 }
+    }
+
+    fn compare(self, a: Point, b: Point) -> Ordering {
+        // Within a half, directions are ordered by the sign of their cross product
+        self.half(a).cmp(&self.half(b)).then_with(|| 0.cmp(&self.cross(a, b)))
+    }
+}
 
-fn remove_by_angle(mut angles: AngleMap) -> Vec<(usize, usize)> {
-    let mut removed_coords = Vec::new();
-    while !angles.is_empty() {
-        let mut to_remove = Vec::new();
-        for (&angle, distances) in angles.iter_mut() {
-            let distance = distances.keys().cloned().next().unwrap();
-            let coord = distances.remove(&distance).unwrap();
-            removed_coords.push(coord);
-            if distances.is_empty() {
-                to_remove.push(angle);
+#[test]
+fn test_sweep() {
+    let check = |sweep: Sweep, expected: &[(isize, isize)]| {
+        let points = expected.iter().map(|&(x, y)| Point::new(x, y)).collect::<Vec<Point>>();
+        for (i, &a) in points.iter().enumerate() {
+            for (j, &b) in points.iter().enumerate() {
+                assert_eq!(sweep.compare(a, b), i.cmp(&j), "{:?} vs {:?}", a, b);
             }
         }
-        for angle in to_remove {
-            angles.remove(&angle);
+    };
+    check(Sweep::default(),
+        &[(0, -1), (1, -3), (1, -1), (1, 0), (2, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (-1, -5)]);
+    check(Sweep {start: Point::new(0, -1), rotation: Rotation::Counterclockwise},
+        &[(0, -1), (-1, -5), (-1, -1), (-1, 0), (-1, 1), (0, 1), (2, 1), (1, 0), (1, -1), (1, -3)]);
+    check(Sweep {start: Point::new(-1, 1), rotation: Rotation::Clockwise},
+        &[(-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1)]);
+}
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+// Split a nonzero vector into a direction with no common factor and the number of steps along it
+fn reduce(d: Point) -> (Point, isize) {
+    let n = gcd(d.x, d.y);
+    (Point::new(d.x / n, d.y / n), n)
+}
+
+#[test]
+fn test_reduce() {
+    assert_eq!(reduce(Point::new(4, -6)), (Point::new(2, -3), 2));
+    assert_eq!(reduce(Point::new(0, -5)), (Point::new(0, -1), 5));
+    assert_eq!(reduce(Point::new(-3, 0)), (Point::new(-1, 0), 3));
+    assert_eq!(reduce(Point::new(3, 5)), (Point::new(3, 5), 1));
+}
+
+// Asteroids grouped by reduced direction from the station in sweep order, each group nearest first
+fn sort_by_angle(coords: &[Point], station: Point, sweep: Sweep) -> Vec<(Point, Vec<Point>)> {
+    let mut map = HashMap::new();
+    for &b in coords.iter().filter(|&&b| b != station) {
+        let (direction, distance) = reduce(b - station);
+        map.entry(direction).or_insert_with(Vec::new).push((distance, b));
+    }
+    let mut angles = map.into_iter()
+        .map(|(direction, mut targets)| {
+            targets.sort_unstable();
+            (direction, targets.into_iter().map(|(_, b)| b).collect())
+        })
+        .collect::<Vec<(Point, Vec<Point>)>>();
+    angles.sort_unstable_by(|a, b| sweep.compare(a.0, b.0));
+    angles
+}
+
+#[test]
+fn test_sort_by_angle() {
+    // Far enough apart that these directions are indistinguishable as f32 angles
+    let coords = [Point::new(0, 0), Point::new(200_000, -199_998), Point::new(99_999, -99_998), Point::new(100_000, -99_999)];
+    let angles = sort_by_angle(&coords, coords[0], Sweep::default());
+    assert_eq!(angles, vec![
+        (Point::new(100_000, -99_999), vec![coords[3], coords[1]]),
+        (Point::new(99_999, -99_998), vec![coords[2]])]);
+}
+
+// Yields each asteroid as the laser hits it, along with the rotation (counting from 1) that hit it
+struct Vaporization {
+    targets: Vec<VecDeque<Point>>,
+    index: usize,
+    rotation: usize,
+    remaining: usize,
+}
+
+impl Iterator for Vaporization {
+    type Item = (usize, Point);
+
+    fn next(&mut self) -> Option<(usize, Point)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            // Start another rotation, forgetting about directions that have been cleared
+            if self.index == self.targets.len() {
+                self.targets.retain(|t| !t.is_empty());
+                self.index = 0;
+                self.rotation += 1;
+            }
+            let i = self.index;
+            self.index += 1;
+            if let Some(coord) = self.targets[i].pop_front() {
+                self.remaining -= 1;
+                return Some((self.rotation, coord));
+            }
         }
     }
-    removed_coords
+}
+
+fn vaporize(coords: &[Point], station: Point, sweep: Sweep) -> Vaporization {
+    let targets = sort_by_angle(coords, station, sweep).into_iter()
+        .map(|(_, targets)| VecDeque::from(targets))
+        .collect::<Vec<VecDeque<Point>>>();
+    let remaining = targets.iter().map(VecDeque::len).sum();
+    Vaporization {index: targets.len(), targets, rotation: 0, remaining}
+}
+
+// The rotation that hits target is one more than the number of asteroids in front of it
+fn rotation_of(coords: &[Point], station: Point, target: Point) -> Option<usize> {
+    if target == station || !coords.contains(&target) {
+        return None;
+    }
+    let (direction, distance) = reduce(target - station);
+    Some(1 + coords.iter()
+        .filter(|&&b| b != station)
+        .map(|&b| reduce(b - station))
+        .filter(|&(d, n)| d == direction && n < distance)
+        .count())
+}
+
+fn nth_vaporized(coords: &[Point], station: Point, sweep: Sweep, n: usize) -> Option<Point> {
+    vaporize(coords, station, sweep).nth(n.checked_sub(1)?).map(|(_, coord)| coord)
 }
 
 #[test]
-fn test_remove_by_angle() {
-    let coords = parse_asteroid_map(
+fn test_vaporize() {
+    let coords = parse_indented_map(
        ".#..##.###...#######
         ##.############..##.
         .#.######.########.#
@@ -83,52 +296,75 @@ fn test_remove_by_angle() {
         ....##.##.###..#####
         .#.#.###########.###
         #.#.#.#####.####.###
-        ###.##.####.##.#..##");
+        ###.##.####.##.#..##").asteroids;
     let visible = compute_visibility(&coords);
     let best = visible.iter().enumerate().max_by_key(|&(_, e)| e).unwrap().0;
-    let angles = sort_by_angle(&coords, best);
-    let removed = remove_by_angle(angles);
-
-    assert_eq!(coords[best], (11, 13));
-    assert_eq!(removed[0], (11, 12));
-    assert_eq!(removed[1], (12, 1));
-    assert_eq!(removed[2], (12, 2));
-    assert_eq!(removed[9], (12, 8));
-    assert_eq!(removed[19], (16, 0));
-    assert_eq!(removed[49], (16, 9));
-    assert_eq!(removed[99], (10, 16));
-    assert_eq!(removed[198], (9, 6));
-    assert_eq!(removed[199], (8, 2));
-    assert_eq!(removed[200], (10, 9));
-    assert_eq!(removed[298], (11, 1));
-}
-
-fn sort_by_angle(coords: &[(usize, usize)], i: usize) -> AngleMap {
-    let a = coords[i];
-    let mut map = BTreeMap::new();
-    for &b in coords[..i].iter().chain(coords[i+1..].iter()) {
-        let dx = a.0 as f32 - b.0 as f32;
-        let dy = a.1 as f32 - b.1 as f32;
-        let angle = NotNan::new((dy.atan2(dx) / PI + 1.5) % 2.).unwrap(); // 0 to 2
-        let distance = NotNan::new(dy.hypot(dx)).unwrap(); // could be hypot2 or even Manhattan distance
-        map.entry(angle).or_insert_with(BTreeMap::new).insert(distance, b);
-    }
-    map
-}
-
-fn compute_visibility(coords: &[(usize, usize)]) -> Vec<usize> {
+    let station = coords[best];
+    let events = vaporize(&coords, station, Sweep::default()).collect::<Vec<(usize, Point)>>();
+    let removed = events.iter().map(|&(_, coord)| coord).collect::<Vec<Point>>();
+
+    assert_eq!(coords[best], Point::new(11, 13));
+    assert_eq!(removed[0], Point::new(11, 12));
+    assert_eq!(removed[1], Point::new(12, 1));
+    assert_eq!(removed[2], Point::new(12, 2));
+    assert_eq!(removed[9], Point::new(12, 8));
+    assert_eq!(removed[19], Point::new(16, 0));
+    assert_eq!(removed[49], Point::new(16, 9));
+    assert_eq!(removed[99], Point::new(10, 16));
+    assert_eq!(removed[198], Point::new(9, 6));
+    assert_eq!(removed[199], Point::new(8, 2));
+    assert_eq!(removed[200], Point::new(10, 9));
+    assert_eq!(removed[298], Point::new(11, 1));
+    assert_eq!(removed.len(), 299);
+
+    // Everything visible goes in the first rotation, and rotations only ever increase
+    assert_eq!(events.iter().filter(|&&(r, _)| r == 1).count(), visible[best]);
+    assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    for &(rotation, coord) in events.iter() {
+        assert_eq!(rotation_of(&coords, station, coord), Some(rotation));
+    }
+    assert_eq!(rotation_of(&coords, station, station), None);
+    assert_eq!(rotation_of(&coords, station, Point::new(0, 0)), None);
+
+    assert_eq!(nth_vaporized(&coords, station, Sweep::default(), 200), Some(Point::new(8, 2)));
+    assert_eq!(nth_vaporized(&coords, station, Sweep::default(), 0), None);
+    assert_eq!(nth_vaporized(&coords, station, Sweep::default(), 300), None);
+
+    // Sweeping the other way from up hits the same first asteroid, then mirrors the order
+    let sweep = Sweep {start: Point::new(0, -1), rotation: Rotation::Counterclockwise};
+    let mirrored = vaporize(&coords, station, sweep).map(|(_, coord)| coord).collect::<Vec<Point>>();
+    assert_eq!(mirrored[0], Point::new(11, 12));
+    assert_eq!(mirrored[1], removed[visible[best] - 1]);
+    assert_eq!(mirrored.len(), removed.len());
+
+    // Starting straight down from a different station
+    let sweep = Sweep {start: Point::new(0, 1), rotation: Rotation::Clockwise};
+    assert_eq!(nth_vaporized(&coords, Point::new(0, 0), sweep, 1), Some(Point::new(0, 1)));
+    assert_eq!(nth_vaporized(&coords, Point::new(0, 0), sweep, 2), Some(Point::new(1, 0)));
+}
+
+// Each asteroid sees exactly one asteroid in every distinct reduced direction
+fn compute_visibility(coords: &[Point]) -> Vec<usize> {
+    coords.par_iter()
+        .map(|&a| {
+            let mut directions = HashSet::with_capacity(coords.len());
+            directions.extend(coords.iter().filter(|&&b| b != a).map(|&b| reduce(b - a).0));
+            directions.len()
+        })
+        .collect()
+}
+
+// Reference implementation checking every pair against every other asteroid
+#[cfg(test)]
+fn compute_visibility_pairwise(coords: &[Point]) -> Vec<usize> {
     let mut visible = vec![0; coords.len()];
 
     // Iterate over each pair of asteroids
     for (i, &a) in coords.iter().enumerate() {
         'next: for (j, &b) in coords[i+1..].iter().enumerate() {
-            // Pre-compute reciprocal of vector length
-            let bax = (b.0 as f32 - a.0 as f32).recip();
-            let bay = (b.1 as f32 - a.1 as f32).recip();
-
             // Check all other asteroids for blocking visibility
             for &c in coords[..i].iter().chain(coords[i+1..i+j+1].iter()).chain(coords[i+j+2..].iter()) {
-                if is_blocking(c, a, bax, bay) {
+                if is_blocking(c, a, b) {
                     continue 'next;
                 }
             }
@@ -141,29 +377,40 @@ fn compute_visibility(coords: &[(usize, usize)]) -> Vec<usize> {
     visible
 }
 
-fn is_blocking(c: (usize, usize), a: (usize, usize), bax: f32, bay: f32) -> bool {
-    const EPSILON: f32 = 10. * f32::EPSILON;
-    match (bax.is_finite(), bay.is_finite()) {
-        (true, true) => { // Diagonal line
-            let tx = (c.0 as f32 - a.0 as f32) * bax;
-            let ty = (c.1 as f32 - a.1 as f32) * bay;
-            (tx - ty).abs() < EPSILON && tx >= 0. && tx <= 1.// && ty >= 0. && ty <= 1.
-        }
-        (false, true) => { // Vertical line
-            let ty = (c.1 as f32 - a.1 as f32) * bay;
-            (c.0 as f32 - a.0 as f32).abs() < EPSILON && ty >= 0. && ty <= 1.
-        }
-        (true, false) => { // Horizontal line
-            let tx = (c.0 as f32 - a.0 as f32) * bax;
-            (c.1 as f32 - a.1 as f32).abs() < EPSILON && tx >= 0. && tx <= 1.
-        }
-        (false, false) => false, // Degenerate line
+#[test]
+fn test_compute_visibility_pairwise() {
+    for &(size, density, seed) in [(15, 30, 1), (20, 60, 2), (20, 90, 3)].iter() {
+        let coords = parse_asteroid_map(&generate_asteroid_map(size, density, seed)).unwrap().asteroids;
+        assert_eq!(compute_visibility(&coords), compute_visibility_pairwise(&coords));
     }
 }
 
+// Whether c lies on the line between a and b, excluding the endpoints
+#[cfg(test)]
+fn is_blocking(c: Point, a: Point, b: Point) -> bool {
+    let ab = b - a;
+    let ac = c - a;
+    let cross = ab.x * ac.y - ab.y * ac.x;
+    let dot = ab.x * ac.x + ab.y * ac.y;
+    cross == 0 && dot > 0 && dot < ab.x * ab.x + ab.y * ab.y
+}
+
+#[test]
+fn test_is_blocking() {
+    let a = Point::new(0, 0);
+    let b = Point::new(6, 3);
+    assert!(is_blocking(Point::new(2, 1), a, b));
+    assert!(is_blocking(Point::new(4, 2), a, b));
+    assert!(!is_blocking(Point::new(8, 4), a, b));
+    assert!(!is_blocking(Point::new(-2, -1), a, b));
+    assert!(!is_blocking(Point::new(3, 1), a, b));
+    assert!(is_blocking(Point::new(0, 2), a, Point::new(0, 5)));
+    assert!(!is_blocking(Point::new(99_999, 99_998), a, Point::new(100_000, 99_999)));
+}
+
 #[cfg(test)]
 fn test_compute_visibility_helper(map: &str, expected: usize) {
-    let coords = parse_asteroid_map(map);
+    let coords = parse_indented_map(map).asteroids;
     let visibility = compute_visibility(&coords);
     assert_eq!(visibility.iter().max(), Some(&expected));
 }
@@ -199,20 +446,221 @@ fn test_compute_visibility() {
                                     .####.###.", 35);
 }
 
-fn parse_asteroid_map(input: &str) -> Vec<(usize, usize)> {
-    input.lines().enumerate() // Iterate over rows
-        .flat_map(|(row, text)| text.trim().chars().enumerate() // Iterate over cols
-            .filter_map(move |(col, token)| if token == '#' { Some((col, row)) } else { None }))
-        .collect()
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Cell {
+    Empty,
+    Asteroid,
+    Station, // An asteroid marked as the monitoring station
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct AsteroidMap {
+    width: usize,
+    height: usize,
+    asteroids: Vec<Point>, // In row-major order, including the station
+    station: Option<Point>,
+}
+
+// '#' is an asteroid, '.' is empty space and 'X' marks at most one station; rows must all be the same width,
+// with nothing else on them, not even whitespace
+fn parse_asteroid_map(input: &str) -> io::Result<AsteroidMap> {
+    let grid = Grid::parse_exact(input, |c| match c {
+        '.' => Some(Cell::Empty),
+        '#' => Some(Cell::Asteroid),
+        'X' => Some(Cell::Station),
+        _ => None,
+    })?;
+
+    let mut station = None;
+    for (point, &cell) in grid.iter() {
+        if cell == Cell::Station && station.replace(point).is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("Second station at line {} column {}", point.y + 1, point.x + 1)));
+        }
+    }
+    let asteroids = grid.iter().filter(|&(_, &cell)| cell != Cell::Empty).map(|(point, _)| point).collect();
+    Ok(AsteroidMap {width: grid.width, height: grid.height, asteroids, station})
+}
+
+impl AsteroidMap {
+    // Inverse of parse_asteroid_map
+    fn to_text(&self) -> String {
+        let mut grid = Grid::new(self.width, self.height, '.');
+        for &asteroid in self.asteroids.iter() {
+            grid[asteroid] = '#';
+        }
+        if let Some(station) = self.station {
+            grid[station] = 'X';
+        }
+        grid.render(|&c| c)
+    }
 }
 
 #[test]
 fn test_parse_asteroid_map() {
-    let map = ".#..#
-               .....
-               #####
-               ....#
-               ...##";
-    let coords = [(1, 0), (4, 0), (0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (4, 3), (3, 4), (4, 4)];
-    assert_eq!(parse_asteroid_map(&map), &coords);
+    let input = ".#..#
+                 .....
+                 #####
+                 ....#
+                 ...##";
+    let map = parse_indented_map(input);
+    let coords = [(1, 0), (4, 0), (0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (4, 3), (3, 4), (4, 4)]
+        .iter().map(|&(x, y)| Point::new(x, y)).collect::<Vec<Point>>();
+    assert_eq!(map.asteroids, coords);
+    assert_eq!((map.width, map.height, map.station), (5, 5, None));
+    assert_eq!(map.to_text(), ".#..#\n.....\n#####\n....#\n...##");
+
+    let map = parse_asteroid_map("#..\n.X.\n...\n...").unwrap();
+    assert_eq!((map.width, map.height, map.station), (3, 4, Some(Point::new(1, 1))));
+    assert_eq!(map.asteroids, &[Point::new(0, 0), Point::new(1, 1)]);
+    assert_eq!(parse_asteroid_map(&map.to_text()).unwrap(), map);
+    assert_eq!(parse_asteroid_map("").unwrap().asteroids, &[]);
+
+    let error = |input| parse_asteroid_map(input).unwrap_err().to_string();
+    assert_eq!(error(".#.\n.#"), "Line 2 has 2 cells, expected 3");
+    assert_eq!(error(".#.\n.o."), "Invalid cell 'o' at line 2 column 2");
+    assert_eq!(error("#.\t#"), "Invalid cell '\\t' at line 1 column 3");
+    assert_eq!(error("X.\n.X"), "Second station at line 2 column 2");
+    assert_eq!(error("\t#.#\n  .#."), "Invalid cell '\\t' at line 1 column 1");
+    assert_eq!(error("#.#\n.#. "), "Invalid cell ' ' at line 2 column 4");
+    assert_eq!(error("  .o."), "Invalid cell ' ' at line 1 column 1");
+    assert_eq!(error("...o."), "Invalid cell 'o' at line 1 column 4");
+    assert_eq!(error("...\n\n..."), "Line 2 has 0 cells, expected 3");
+}
+
+// Test maps are indented to line up in the source
+#[cfg(test)]
+fn parse_indented_map(input: &str) -> AsteroidMap {
+    parse_asteroid_map(&input.lines().map(str::trim).collect::<Vec<&str>>().join("\n")).unwrap()
+}
+
+// Square map where each cell holds an asteroid with the given percent chance
+fn generate_asteroid_map(size: usize, density: u64, seed: u64) -> String {
+    let mut rng = Xorshift::new(seed);
+    (0..size)
+        .map(|_| (0..size).map(|_| if rng.below(100) < density { '#' } else { '.' }).collect::<String>())
+        .collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_generate_asteroid_map() {
+    // About density percent of the cells hold asteroids, and the map parses at the requested size
+    for &density in [0, 10, 50, 100].iter() {
+        let map = parse_asteroid_map(&generate_asteroid_map(100, density, 7)).unwrap();
+        assert_eq!((map.width, map.height), (100, 100));
+        let percent = map.asteroids.len() as f64 / 100.0;
+        assert!((percent - density as f64).abs() <= 3.0, "{}% asteroids for density {}", percent, density);
+    }
+}
+
+fn bench_visibility(size: usize, density: u64) -> SuperResult<()> {
+    let coords = parse_asteroid_map(&generate_asteroid_map(size, density, 1))?.asteroids;
+    let start = Instant::now();
+    let visible = compute_visibility(&coords);
+    let elapsed = start.elapsed();
+    let best = visible.iter().enumerate().max_by_key(|&(_, e)| e).map(|(i, &e)| (coords[i], e));
+    println!("{}x{} map with {} asteroids: best {:?} in {:.3?}", size, size, coords.len(), best, elapsed);
+    Ok(())
+}
+
+const HEAT_LEVELS: u8 = 8;
+
+// Empty space, then heat levels from blue (sees the fewest) to red (sees the most), then the station in white
+fn heat_palette() -> Vec<Color> {
+    let top = HEAT_LEVELS as u32 - 1;
+    let mut palette = vec![[0, 0, 0]];
+    palette.extend((0..HEAT_LEVELS as u32).map(|i| [(255 * i / top) as u8, 64, (255 * (top - i) / top) as u8]));
+    palette.push([255, 255, 255]);
+    palette
+}
+
+// Pixels index into heat_palette: 0 for empty space, a heat level scaled to the best count, or the station
+fn heatmap(map: &AsteroidMap, visible: &[usize], station: Point) -> Bitmap {
+    let mut bitmap = Bitmap::new(map.width, map.height, 0);
+    let max = visible.iter().cloned().max().unwrap_or(0).max(1);
+    for (&coord, &count) in map.asteroids.iter().zip(visible) {
+        bitmap[coord] = 1 + (count * (HEAT_LEVELS as usize - 1) / max) as u8;
+    }
+    if bitmap.contains(station) {
+        bitmap[station] = HEAT_LEVELS + 1;
+    }
+    bitmap
+}
+
+#[test]
+fn test_heatmap() {
+    let map = parse_indented_map(
+       ".#..#
+        .....
+        #####
+        ....#
+        ...##");
+    let visible = compute_visibility(&map.asteroids);
+    let bitmap = heatmap(&map, &visible, Point::new(3, 4));
+    assert_eq!(bitmap.to_text(&['.', '0', '1', '2', '3', '4', '5', '6', '7', 'X']),
+        ".6..6\n.....\n56664\n....6\n...X6");
+    assert_eq!(heat_palette().len(), HEAT_LEVELS as usize + 2);
+    assert_eq!(heat_palette()[1], [0, 64, 255]);
+    assert_eq!(heat_palette()[HEAT_LEVELS as usize], [255, 64, 0]);
+}
+
+// Truecolor terminal rendering, with asteroids as # and the station as X
+fn to_ansi(bitmap: &Bitmap, palette: &[Color]) -> String {
+    let station = palette.len() as u8 - 1;
+    bitmap.rows()
+        .map(|row| row.iter().map(|&p| match p {
+            0 => ".".to_string(),
+            _ => {
+                let [r, g, b] = palette[p as usize];
+                format!("\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, if p == station { 'X' } else { '#' })
+            }
+        }).collect::<String>())
+        .collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_to_ansi() {
+    let bitmap = Bitmap::from_cells(3, 1, vec![0, 1, 2]).unwrap();
+    let palette = [[0, 0, 0], [1, 2, 3], [255, 255, 255]];
+    assert_eq!(to_ansi(&bitmap, &palette), ".\x1b[38;2;1;2;3m#\x1b[0m\x1b[38;2;255;255;255mX\x1b[0m");
+}
+
+// Map with the first count asteroids to be vaporized numbered from 1, the station as X and the rest as #
+fn label_vaporization(map: &AsteroidMap, station: Point, sweep: Sweep, count: usize) -> String {
+    let mut labels = Grid::new(map.width, map.height, ".".to_string());
+    for &coord in map.asteroids.iter() {
+        labels[coord] = "#".to_string();
+    }
+    for (i, (_, coord)) in vaporize(&map.asteroids, station, sweep).take(count).enumerate() {
+        labels[coord] = (i + 1).to_string();
+    }
+    if labels.contains(station) {
+        labels[station] = "X".to_string();
+    }
+
+    let cell = labels.cells.iter().map(String::len).max().unwrap_or(1);
+    labels.rows()
+        .map(|row| row.iter().map(|label| format!("{:>1$}", label, cell)).collect::<Vec<String>>().join(" "))
+        .collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_label_vaporization() {
+    let map = parse_indented_map(
+       ".#....#####...#..
+        ##...##.#####..##
+        ##...#...#.#####.
+        ..#.....X...###..
+        ..#.#.....#....##");
+    let station = map.station.unwrap();
+    assert_eq!(station, Point::new(8, 3));
+    let labels = label_vaporization(&map, station, Sweep::default(), 9);
+    assert_eq!(labels.replace(' ', ""),
+        ".#....###24...#..\n##...##.13#67..9#\n##...#...5.8####.\n..#.....X...###..\n..#.#.....#....##");
+    assert!(labels.starts_with(". # . . . . # # # 2 4"));
+
+    // Labels are padded to the widest one
+    let labels = label_vaporization(&map, station, Sweep::default(), 10);
+    assert!(labels.starts_with(" .  #  ."));
+    assert!(labels.contains(" 8 10  #"));
 }
@@ -1,15 +1,23 @@
 #[macro_use]
 extern crate quick_error;
 
-use rayon::prelude::*;
-
-use intcode::Intcode;
-
-use std::borrow::Cow;
-use std::collections::HashMap;
-use std::env;
+use aoc2019::cli::Args;
+use aoc2019::ocr;
+#[cfg(test)]
+use aoc2019::ocr::Font;
+use aoc2019::raster::{self, Bitmap, RenderOptions};
+use aoc2019::grid::{Bounds, Direction, Point};
+#[cfg(test)]
+use aoc2019::grid::Turn;
+use aoc2019::robot::{HullMap, IntcodeController, Move, Robot};
+
+use std::fs;
 use std::io;
+use std::iter;
 use std::num::ParseIntError;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
 quick_error! {
     #[derive(Debug)]
@@ -22,103 +30,157 @@ quick_error! {
 type SuperResult<T> = Result<T, SuperError>;
 
 fn main() -> SuperResult<()> {
-    let input = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        std::fs::read_to_string(name.as_ref())?
-    };
+    let args = Args::from_env();
+    let input = fs::read_to_string(args.input())?;
 
     let memory = input.trim().split(',')
         .map(str::parse)
         .collect::<Result<Vec<isize>, ParseIntError>>()?;
 
-    println!("Part 1: {:?}", paint_hull(&memory, false)?.len());
+    println!("Part 1: {:?}", paint_hull(&memory, false, false)?.hull.len());
 
     println!("Part 2:");
-    let hull = paint_hull(&memory, true)?;
-    display_hull(&hull);
+    let animate = args.flag("animate");
+    let frames = args.flag("frames");
+    let robot = paint_hull(&memory, true, animate.is_some() || frames.is_some())?;
+    let hull = render_hull(&robot.hull)?;
+    println!("{}", hull.to_text(&['🀆', '🀫']));
+    if let Ok(text) = ocr::recognize(&hull, 1) {
+        println!("{}", text);
+    }
+
+    // Optionally export the hull, e.g. day11 input hull.ppm 10 000000,ffffff
+    let options = RenderOptions::parse(args.get(2), args.get(3))?;
+    if let Some(path) = args.get(1) {
+        raster::save(path, &hull, &options)?;
+    }
+
+    // Redraw the hull for each move, e.g. --animate=20 for 20 ms per frame
+    if let Some(delay) = animate {
+        let delay = Duration::from_millis(if delay.is_empty() { 50 } else { delay.parse()? });
+        replay_hull(&PART_TWO_PANELS, &robot.moves, |_, frame| {
+            println!("\x1b[2J\x1b[H{}", frame.to_text(&['.', '#', '^', '>', 'v', '<']));
+            thread::sleep(delay);
+            Ok(())
+        })?;
+    }
+
+    // Write each frame as a numbered image, e.g. --frames=out for out/frame00000.ppm
+    if let Some(dir) = frames {
+        // Pad the palette so the robot shows up in red
+        let mut options = options;
+        options.palette.truncate(2);
+        options.palette.resize(6, [255, 0, 0]);
+        replay_hull(&PART_TWO_PANELS, &robot.moves, |i, frame| {
+            let path = Path::new(dir).join(format!("frame{:05}.ppm", i));
+            Ok(raster::save(path, frame, &options)?)
+        })?;
+    }
 
     Ok(())
 }
 
-type HullMap = HashMap<(isize, isize), isize>;
+// In part two, we start on a lone white panel
+const PART_TWO_PANELS: [(Point, isize); 1] = [(Point::ORIGIN, 1)];
 
-fn paint_hull(memory: &[isize], part_two: bool) -> SuperResult<HullMap> {
-    let mut program = Intcode::new(&memory);
-    program.run()?;
-    
-    let mut position = (0, 0);
-    let mut direction = 0; // The robot starts facing up
-    let mut hull = HashMap::new();
+fn paint_hull(memory: &[isize], part_two: bool, record: bool) -> SuperResult<Robot> {
+    let mut controller = IntcodeController::new(memory)?;
 
-    // In part two, we start on a lone white panel
-    if part_two {
-        hull.insert((0, 0), 1);
-    }
+    let mut robot = if part_two {
+        Robot::with_panels(&PART_TWO_PANELS)
+    } else {
+        Robot::new()
+    };
+    robot.record = record;
 
-    while program.resume(hull.get(&position).cloned().unwrap_or(0))? {
-        let turn = program.output.pop().unwrap(); 
-        let color = program.output.pop().unwrap();
+    robot.run(&mut controller)?;
 
-        // 0 means black, and 1 means white
-        hull.insert(position, color);
+    Ok(robot)
+}
 
-        // 0 means turn left 90 degrees, and 1 means turn right 90 degrees
-        direction = (direction + turn * 2 + 3) % 4;
+// Replay recorded moves over the starting panels, drawing the robot as 2 + its heading
+fn replay_hull<F>(panels: &[(Point, isize)], moves: &[Move], mut show: F) -> SuperResult<()>
+    where F: FnMut(usize, &Bitmap) -> SuperResult<()>
+{
+    // Size every frame to fit everywhere the robot has been
+    let bounds = Bounds::from_points(panels.iter().map(|&(p, _)| p)
+        .chain(iter::once(Point::ORIGIN))
+        .chain(moves.iter().map(|m| m.heading.step(m.position)))).unwrap();
+
+    let mut canvas = Bitmap::new(bounds.width(), bounds.height(), 0);
+    for &(p, color) in panels {
+        canvas[p - bounds.min] = color as u8;
+    }
 
-        // After the robot turns, it should always move forward exactly one panel
-        position = match direction {
-            0 => (position.0, position.1 - 1), // up
-            1 => (position.0 + 1, position.1), // right
-            2 => (position.0, position.1 + 1), // down
-            3 => (position.0 - 1, position.1), // left
-            _ => panic!("Corrupted direction"),
+    let mut robot = (Point::ORIGIN, Direction::Up);
+    for i in 0..=moves.len() {
+        if i > 0 {
+            let m = moves[i - 1];
+            canvas[m.position - bounds.min] = m.color as u8;
+            robot = (m.heading.step(m.position), m.heading);
         }
+        let mut frame = canvas.clone();
+        frame[robot.0 - bounds.min] = 2 + robot.1 as u8;
+        show(i, &frame)?;
     }
 
-    Ok(hull)
+    Ok(())
 }
 
-fn display_hull(hull: &HullMap) {
-    let (left, top, right, bottom) = compute_bounds(hull);
-    let rows = bottom - top + 1;
-    let cols = right - left + 1;
-
-    let image = (0..rows).into_par_iter().map(|row| {
-        (0..cols).into_par_iter().map(|col| {
-            let x = left + col;
-            let y = top + row;
-            match hull.get(&(x, y)).cloned().unwrap_or(0) {
-                1 => '🀫',
-                0 => '🀆',
-                _ => panic!("Invalid color"),
-            }
-        }).collect::<String>()
-    }).collect::<Vec<String>>().join("\n");
-
-    println!("{}", image);
+#[test]
+fn test_replay_hull() {
+    let mut script = vec![(1, Turn::Left), (0, Turn::Left), (1, Turn::Left), (1, Turn::Left),
+        (0, Turn::Right), (1, Turn::Left), (1, Turn::Left)].into_iter();
+    let mut robot = Robot::new();
+    robot.record = true;
+    robot.run(&mut |_| script.next()).unwrap();
+
+    let mut frames = vec![];
+    replay_hull(&[], &robot.moves, |i, frame| {
+        assert_eq!(i, frames.len());
+        frames.push(frame.to_text(&['.', '#', '^', '>', 'v', '<']));
+        Ok(())
+    }).unwrap();
+
+    assert_eq!(frames.len(), 8);
+    assert_eq!(frames[0], "...\n.^.\n...");
+    assert_eq!(frames[1], "...\n<#.\n...");
+    assert_eq!(frames[4], "...\n.^.\n##.");
+    assert_eq!(frames[7], ".<#\n..#\n##.");
 }
 
-fn compute_bounds(hull: &HullMap) -> (isize, isize, isize, isize) {
-    let mut left = isize::MAX;
-    let mut top = isize::MAX;
-    let mut right = isize::MIN;
-    let mut bottom = isize::MIN;
-    for (&(x, y), &color) in hull.iter() {
-        if color == 1 {
-            if x < left {
-                left = x;
-            }
-            if x > right {
-                right = x;
-            }
-            if y < top {
-                top = y;
-            }
-            if y > bottom {
-                bottom = y;
-            }
-        }
+// Crop the hull to its white panels
+fn render_hull(hull: &HullMap) -> io::Result<Bitmap> {
+    let bounds = match hull.bounds_where(|&color| color == 1) {
+        Some(bounds) => bounds,
+        None => return Ok(Bitmap::new(0, 0, 0)),
+    };
+
+    let image = hull.to_grid(bounds, 0);
+    image.cells.iter().enumerate()
+        .map(|(i, &color)| match color {
+            0..=1 => Ok(color as u8),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("Invalid color {} at {:?}", color, image.point_of(i) + bounds.min))),
+        })
+        .collect::<io::Result<Vec<u8>>>()
+        .and_then(|pixels| Bitmap::from_cells(image.width, image.height, pixels))
+}
+
+#[test]
+fn test_render_hull() {
+    // Paint the message somewhere off the origin, leaving some stray black panels around it
+    let message = ocr::render("HJKL", Font::Small).unwrap();
+    let mut hull = HullMap::new();
+    for (p, &pixel) in message.iter() {
+        hull.insert(p + Point::new(-3, 5), pixel as isize);
     }
-    (left, top, right, bottom)
+    hull.insert(Point::new(-10, -10), 0);
+
+    let image = render_hull(&hull).unwrap();
+    assert_eq!((image.width, image.height), (message.width, message.height));
+    assert_eq!(ocr::recognize(&image, 1).unwrap(), "HJKL");
+
+    hull.insert(Point::new(0, 7), 2);
+    assert!(render_hull(&hull).is_err());
 }
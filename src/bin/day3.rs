@@ -1,12 +1,17 @@
 #[macro_use]
 extern crate quick_error;
 
-use std::borrow::Cow;
-use std::env;
-use std::fs::File;
+use aoc2019::cli::Args;
+use aoc2019::grid::{Bounds, Grid, Point};
+use aoc2019::rng::Xorshift;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{self, prelude::*, BufReader};
 use std::num::ParseIntError;
-use std::ops::{Add, AddAssign, Sub};
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::time::Instant;
 
 quick_error! {
     #[derive(Debug)]
@@ -24,24 +29,41 @@ enum Cardinal {
     Down(u32),
     Left(u32),
     Right(u32),
+    UpLeft(u32),
+    UpRight(u32),
+    DownLeft(u32),
+    DownRight(u32),
+    Forward(u32), // Towards positive z
+    Backward(u32),
 }
 
+// Every direction, orthogonal ones first and then diagonal ones
+const DIRECTIONS: [fn(u32) -> Cardinal; 10] = [Cardinal::Up, Cardinal::Down, Cardinal::Left, Cardinal::Right,
+    Cardinal::UpLeft, Cardinal::UpRight, Cardinal::DownLeft, Cardinal::DownRight, Cardinal::Forward, Cardinal::Backward];
+
 impl Cardinal {
-    fn is_horizontal(self) -> bool {
+    // A single move in this direction; diagonal moves change both x and y at once
+    fn unit(self) -> Position {
         match self {
-            Cardinal::Up(_) => false,
-            Cardinal::Down(_) => false,
-            Cardinal::Left(_) => true,
-            Cardinal::Right(_) => true,
+            Cardinal::Up(_) => Position::new(0, 1, 0),
+            Cardinal::Down(_) => Position::new(0, -1, 0),
+            Cardinal::Left(_) => Position::new(-1, 0, 0),
+            Cardinal::Right(_) => Position::new(1, 0, 0),
+            Cardinal::UpLeft(_) => Position::new(-1, 1, 0),
+            Cardinal::UpRight(_) => Position::new(1, 1, 0),
+            Cardinal::DownLeft(_) => Position::new(-1, -1, 0),
+            Cardinal::DownRight(_) => Position::new(1, -1, 0),
+            Cardinal::Forward(_) => Position::new(0, 0, 1),
+            Cardinal::Backward(_) => Position::new(0, 0, -1),
         }
     }
 
-    fn distance(self) -> u32 {
+    // Number of moves
+    fn distance(self) -> usize {
         match self {
-            Cardinal::Up(value) => value,
-            Cardinal::Down(value) => value,
-            Cardinal::Left(value) => value,
-            Cardinal::Right(value) => value,
+            Cardinal::Up(value) | Cardinal::Down(value) | Cardinal::Left(value) | Cardinal::Right(value) |
+            Cardinal::UpLeft(value) | Cardinal::UpRight(value) | Cardinal::DownLeft(value) | Cardinal::DownRight(value) |
+            Cardinal::Forward(value) | Cardinal::Backward(value) => value as usize,
         }
     }
 }
@@ -49,16 +71,22 @@ impl Cardinal {
 type Wire = Vec<Cardinal>;
 type WireSlice<'a> = &'a [Cardinal];
 
-// Parse str "U2" into Cardinal::Up(2)
+// Parse str "U2" into Cardinal::Up(2), or "UR2" into Cardinal::UpRight(2)
 fn parse_cardinal(input: &str) -> SuperResult<Cardinal> {
-    let mut chars = input.trim().chars();
-    let cardinal = chars.next();
-    let value = chars.as_str().parse()?;
+    let input = input.trim();
+    let (cardinal, value) = input.split_at(input.find(|c: char| c.is_ascii_digit()).unwrap_or(input.len()));
+    let value = value.parse()?;
     match cardinal {
-        Some('U') => Ok(Cardinal::Up(value)),
-        Some('D') => Ok(Cardinal::Down(value)),
-        Some('L') => Ok(Cardinal::Left(value)),
-        Some('R') => Ok(Cardinal::Right(value)),
+        "U" => Ok(Cardinal::Up(value)),
+        "D" => Ok(Cardinal::Down(value)),
+        "L" => Ok(Cardinal::Left(value)),
+        "R" => Ok(Cardinal::Right(value)),
+        "UL" => Ok(Cardinal::UpLeft(value)),
+        "UR" => Ok(Cardinal::UpRight(value)),
+        "DL" => Ok(Cardinal::DownLeft(value)),
+        "DR" => Ok(Cardinal::DownRight(value)),
+        "F" => Ok(Cardinal::Forward(value)),
+        "B" => Ok(Cardinal::Backward(value)),
         _ => Err(io::Error::new(io::ErrorKind::InvalidData,
             format!("Invalid cardinal {}", input)).into()),
     }
@@ -73,6 +101,13 @@ fn test_parse_cardinal() {
     assert!(parse_cardinal("A1").is_err());
     assert!(parse_cardinal("").is_err());
     assert!(parse_cardinal("U").is_err());
+    assert_eq!(parse_cardinal("UR5").ok(), Some(Cardinal::UpRight(5)));
+    assert_eq!(parse_cardinal("DL12").ok(), Some(Cardinal::DownLeft(12)));
+    assert_eq!(parse_cardinal("F2").ok(), Some(Cardinal::Forward(2)));
+    assert_eq!(parse_cardinal("B0").ok(), Some(Cardinal::Backward(0)));
+    assert!(parse_cardinal("RU5").is_err());
+    assert!(parse_cardinal("UD5").is_err());
+    assert!(parse_cardinal("U-5").is_err());
 }
 
 // Parse str "R2,D2" into vec![Cardinal::Right(2), Cardinal::Down(2)]
@@ -86,144 +121,713 @@ fn parse_wire(input: &str) -> SuperResult<Wire> {
 fn test_parse_wire() {
     assert_eq!(parse_wire(" U7 , R6 , D4 , L4 ").ok(),
         Some(vec![Cardinal::Up(7), Cardinal::Right(6), Cardinal::Down(4), Cardinal::Left(4)]));
+    assert_eq!(parse_wire("UL1,F3").ok(), Some(vec![Cardinal::UpLeft(1), Cardinal::Forward(3)]));
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-struct Coordinate (i32, i32);
+// Unlike the screen coordinates of the grid, wires go up towards positive y; only F and B leave the z = 0 plane
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+struct Position {
+    x: isize,
+    y: isize,
+    z: isize,
+}
 
-impl Coordinate {
-    fn distance(self) -> u32 {
-        self.0.abs() as u32 + self.1.abs() as u32
+impl Position {
+    const ORIGIN: Position = Position::new(0, 0, 0);
+
+    const fn new(x: isize, y: isize, z: isize) -> Self {
+        Position {x, y, z}
+    }
+
+    fn manhattan(self) -> usize {
+        self.x.unsigned_abs() + self.y.unsigned_abs() + self.z.unsigned_abs()
+    }
+
+    // Distance when a diagonal move costs the same as any other
+    fn chebyshev(self) -> usize {
+        self.x.unsigned_abs().max(self.y.unsigned_abs()).max(self.z.unsigned_abs())
+    }
+
+    // Where the position lands on a drawing of the x-y plane
+    fn plane(self) -> Point {
+        Point::new(self.x, self.y)
     }
 }
 
-#[test]
-fn test_distance() {
-    assert_eq!(Coordinate(2, 3).distance(), 5);
-    assert_eq!(Coordinate(-1, -2).distance(), 3);
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.z == 0 {
+            write!(f, "{},{}", self.x, self.y)
+        } else {
+            write!(f, "{},{},{}", self.x, self.y, self.z)
+        }
+    }
+}
+
+impl Add for Position {
+    type Output = Self;
+    fn add(self, rhs: Position) -> Self {
+        Position::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
 }
 
-impl Add<Cardinal> for Coordinate {
+impl Sub for Position {
+    type Output = Self;
+    fn sub(self, rhs: Position) -> Self {
+        Position::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Mul<isize> for Position {
+    type Output = Self;
+    fn mul(self, rhs: isize) -> Self {
+        Position::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Add<Cardinal> for Position {
     type Output = Self;
     fn add(self, rhs: Cardinal) -> Self {
-        #[allow(clippy::suspicious_arithmetic_impl)] // this is a silly lint
-        match rhs {
-            Cardinal::Up(value) => Coordinate(self.0, self.1 + value as i32),
-            Cardinal::Down(value) => Coordinate(self.0, self.1 - value as i32),
-            Cardinal::Left(value) => Coordinate(self.0 - value as i32, self.1),
-            Cardinal::Right(value) => Coordinate(self.0 + value as i32, self.1),
-        }
+        self + rhs.unit() * rhs.distance() as isize
     }
 }
 
-impl AddAssign<Cardinal> for Coordinate {
+impl AddAssign<Cardinal> for Position {
     fn add_assign(&mut self, rhs: Cardinal) {
         *self = *self + rhs;
     }
 }
 
-impl Sub for Coordinate {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self {
-        Coordinate(self.0 - rhs.0, self.1 - rhs.1)
-    }
+#[test]
+fn test_distance() {
+    assert_eq!(Position::new(2, 3, 0).manhattan(), 5);
+    assert_eq!(Position::new(-1, -2, 0).manhattan(), 3);
+    assert_eq!(Position::new(-1, -2, 4).manhattan(), 7);
+    assert_eq!(Position::new(-1, -2, 0).chebyshev(), 2);
+    assert_eq!(Position::new(-1, 2, -3).chebyshev(), 3);
 }
 
 #[test]
 fn test_coordinates() {
-    assert_eq!(Coordinate(1, 2) + Cardinal::Up(2), Coordinate(1, 4));
-    assert_eq!(Coordinate(-1, 2) + Cardinal::Down(3), Coordinate(-1, -1));
-    assert_eq!(Coordinate(1, 2) + Cardinal::Left(2), Coordinate(-1, 2));
-    assert_eq!(Coordinate(-1, 2) + Cardinal::Right(3), Coordinate(2, 2));
-    assert_eq!(Coordinate(4, 6) - Coordinate(3, 4), Coordinate(1, 2));
-}
-
-fn is_contained(p: i32, a: i32, b: i32) -> bool {
-    if a < b {
-        p >= a && p <= b
-    } else {
-        p >= b && p <= a
-    }
-}
-
-fn intersect_lines(origin1: Coordinate, cardinal1: Cardinal, origin2: Coordinate, cardinal2: Cardinal) -> Option<Coordinate> {
-    let end1 = origin1 + cardinal1;
-    let end2 = origin2 + cardinal2;
-    if cardinal1.is_horizontal() == cardinal2.is_horizontal() {
-        // Both lines horizontal or both vertical; possibly match at endpoints, but ignore overlap
-        if origin1 == origin2 {
-            Some(origin1)
-        } else if origin1 + cardinal1 == origin2 {
-            Some(origin2)
-        } else if origin1 == origin2 + cardinal2 {
-            Some(origin1)
-        } else if origin1 + cardinal1 == origin2 + cardinal2 {
-            Some(origin1 + cardinal1)
-        } else {
-            None
+    assert_eq!(Position::new(1, 2, 0) + Cardinal::Up(2), Position::new(1, 4, 0));
+    assert_eq!(Position::new(-1, 2, 0) + Cardinal::Down(3), Position::new(-1, -1, 0));
+    assert_eq!(Position::new(1, 2, 0) + Cardinal::Left(2), Position::new(-1, 2, 0));
+    assert_eq!(Position::new(-1, 2, 0) + Cardinal::Right(3), Position::new(2, 2, 0));
+    assert_eq!(Position::new(4, 6, 0) - Position::new(3, 4, 0), Position::new(1, 2, 0));
+    assert_eq!(Position::new(0, 0, 0) + Cardinal::UpRight(5), Position::new(5, 5, 0));
+    assert_eq!(Position::new(1, 1, 0) + Cardinal::DownLeft(2), Position::new(-1, -1, 0));
+    assert_eq!(Position::new(1, 1, 0) + Cardinal::Backward(2), Position::new(1, 1, -2));
+    assert_eq!(Position::new(1, -1, 3).to_string(), "1,-1,3");
+    assert_eq!(Position::new(1, -1, 0).to_string(), "1,-1");
+}
+
+// How far a crossing is from the origin
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+}
+
+impl Metric {
+    fn parse(input: &str) -> SuperResult<Self> {
+        match input {
+            "manhattan" => Ok(Metric::Manhattan),
+            "chebyshev" => Ok(Metric::Chebyshev),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown metric {}", input)).into()),
         }
-    } else if cardinal1.is_horizontal() {
-        // First line is horizontal, second line is vertical
-        if is_contained(origin2.0, origin1.0, end1.0) && is_contained(origin1.1, origin2.1, end2.1) {
-            Some(Coordinate(origin2.0, origin1.1))
+    }
+
+    fn distance(self, position: Position) -> usize {
+        match self {
+            Metric::Manhattan => position.manhattan(),
+            Metric::Chebyshev => position.chebyshev(),
+        }
+    }
+}
+
+// Straight piece of a wire, with the steps taken along the wire before reaching its start
+#[derive(Copy, Clone, Debug)]
+struct Segment {
+    wire: usize,
+    start: Position,
+    unit: Position,
+    length: isize,
+    steps: usize,
+}
+
+// Two directions in the z = 0 plane, which segments along either of them can be swept in like segments along x and y.
+// A point's coordinates in the frame are scaled by the determinant so that they stay whole; with two diagonals
+// that's 2, and the points between the grid's points are skipped
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Frame {
+    along: Position,
+    across: Position,
+}
+
+impl Frame {
+    const fn new(along: (isize, isize), across: (isize, isize)) -> Self {
+        Frame {along: Position::new(along.0, along.1, 0), across: Position::new(across.0, across.1, 0)}
+    }
+
+    fn determinant(&self) -> isize {
+        self.along.x * self.across.y - self.along.y * self.across.x
+    }
+
+    // How far along and across the point is, times the determinant
+    fn coordinates(&self, point: Position) -> (isize, isize) {
+        (point.x * self.across.y - point.y * self.across.x, self.along.x * point.y - self.along.y * point.x)
+    }
+
+    fn position(&self, along: isize, across: isize) -> Option<Position> {
+        let d = self.determinant();
+        let point = self.along * along + self.across * across;
+        if point.x % d == 0 && point.y % d == 0 { Some(Position::new(point.x / d, point.y / d, 0)) } else { None }
+    }
+}
+
+// Every pair of the directions in the z = 0 plane, so that segments along any two of them are swept together
+const FRAMES: [Frame; 6] = [
+    Frame::new((1, 0), (0, 1)),
+    Frame::new((1, 1), (-1, 1)),
+    Frame::new((1, 0), (1, 1)),
+    Frame::new((1, 0), (-1, 1)),
+    Frame::new((0, 1), (1, 1)),
+    Frame::new((0, 1), (-1, 1)),
+];
+
+// Line and range of a segment along one of the directions of a frame, which the sweep works with
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Axis {
+    frame: Frame,
+    horizontal: bool, // Along the frame's first direction
+    line: isize, // Coordinate across a horizontal segment, or along a vertical one
+    lo: isize, // Range of the other coordinate
+    hi: isize,
+}
+
+impl Axis {
+    // The point at t on the segment's line, unless it falls between the grid's points
+    fn point(&self, t: isize) -> Option<Position> {
+        if self.horizontal { self.frame.position(t, self.line) } else { self.frame.position(self.line, t) }
+    }
+}
+
+impl Segment {
+    fn point(&self, t: isize) -> Position {
+        self.start + self.unit * t
+    }
+
+    // Every move changes each coordinate by at most one, so this counts the moves from the start
+    fn steps_to(&self, point: Position) -> usize {
+        self.steps + (point - self.start).chebyshev()
+    }
+
+    fn in_plane(&self) -> bool {
+        self.start.z == 0 && self.unit.z == 0
+    }
+
+    fn axis(&self, frame: Frame) -> Option<Axis> {
+        if !self.in_plane() {
+            return None;
+        }
+        let (a, b) = (frame.coordinates(self.start), frame.coordinates(self.point(self.length)));
+        if self.unit == frame.along || self.unit == frame.along * -1 {
+            Some(Axis {frame, horizontal: true, line: a.1, lo: a.0.min(b.0), hi: a.0.max(b.0)})
+        } else if self.unit == frame.across || self.unit == frame.across * -1 {
+            Some(Axis {frame, horizontal: false, line: a.0, lo: a.1.min(b.1), hi: a.1.max(b.1)})
         } else {
             None
         }
-    } else {
-        // First line is vertical, second line is horizontal
-        if is_contained(origin1.0, origin2.0, end2.0) && is_contained(origin2.1, origin1.1, end1.1) {
-            Some(Coordinate(origin1.0, origin2.1))
+    }
+}
+
+fn wire_segments(wire: WireSlice, index: usize) -> Vec<Segment> {
+    let mut start = Position::ORIGIN;
+    let mut steps = 0;
+    wire.iter()
+        .map(|&cardinal| {
+            let segment = Segment {wire: index, start, unit: cardinal.unit(), length: cardinal.distance() as isize, steps};
+            start += cardinal;
+            steps += cardinal.distance();
+            segment
+        })
+        .collect()
+}
+
+#[test]
+fn test_wire_segments() {
+    let segments = wire_segments(&parse_wire("R8,U5,L5,D3").unwrap(), 1);
+    assert_eq!(segments.len(), 4);
+    let frame = FRAMES[0];
+    assert_eq!(segments[2].axis(frame), Some(Axis {frame, horizontal: true, line: 5, lo: 3, hi: 8}));
+    assert_eq!(segments[3].axis(frame), Some(Axis {frame, horizontal: false, line: 3, lo: 2, hi: 5}));
+    assert_eq!(segments[3].axis(FRAMES[1]), None);
+    assert_eq!(segments[3].steps_to(Position::new(3, 2, 0)), 21);
+    assert_eq!(segments[2].point(4), Position::new(4, 5, 0));
+
+    let segments = wire_segments(&parse_wire("UR2,F1,R2").unwrap(), 0);
+    assert!(FRAMES.iter().all(|&frame| segments[1].axis(frame).is_none() && segments[2].axis(frame).is_none()));
+    assert_eq!(segments[2].steps_to(Position::new(4, 2, 1)), 5); // Along x, but out of the z = 0 plane
+    assert_eq!(segments[0].axis(FRAMES[0]), None);
+
+    // Diagonals are swept in a frame of their own, whose lines also meet between the grid's points
+    let frame = FRAMES[1];
+    assert_eq!(frame.determinant(), 2);
+    assert_eq!(segments[0].axis(frame), Some(Axis {frame, horizontal: true, line: 0, lo: 0, hi: 4}));
+    assert_eq!(frame.position(4, 0), Some(Position::new(2, 2, 0)));
+    assert_eq!(frame.position(3, 0), None);
+    let frame = FRAMES[2];
+    assert_eq!(segments[0].axis(frame), Some(Axis {frame, horizontal: false, line: 0, lo: 0, hi: 2}));
+    assert_eq!(frame.coordinates(Position::new(4, 2, 0)), (2, 2));
+    assert_eq!(frame.position(2, 2), Some(Position::new(4, 2, 0)));
+    assert_eq!(segments[2].steps_to(Position::new(4, 2, 1)), 5);
+}
+
+// At the same x, horizontal segments are added before and removed after the vertical ones look them up
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Event {
+    Insert,
+    Query,
+    Remove,
+}
+
+// Sweep across the frame's first coordinate, keeping the horizontal segments under the sweep ordered by the second so that
+// each vertical segment only visits the horizontal segments it crosses
+fn perpendicular_crossings<F>(segments: &[Segment], axes: &[Axis], mut found: F)
+    where F: FnMut(&Segment, &Segment, Position)
+{
+    let mut events = Vec::with_capacity(segments.len() * 2);
+    for (i, s) in axes.iter().enumerate() {
+        if s.horizontal {
+            events.push((s.lo, Event::Insert, i));
+            events.push((s.hi, Event::Remove, i));
         } else {
-            None
+            events.push((s.line, Event::Query, i));
+        }
+    }
+    events.sort_unstable();
+
+    let mut active = BTreeSet::new();
+    for (_, event, i) in events {
+        let s = &axes[i];
+        match event {
+            Event::Insert => { active.insert((s.line, i)); }
+            Event::Remove => { active.remove(&(s.line, i)); }
+            Event::Query => for &(y, j) in active.range((s.lo, 0)..=(s.hi, usize::MAX)) {
+                if let Some(point) = s.point(y).filter(|_| segments[j].wire != segments[i].wire) {
+                    found(&segments[j], &segments[i], point);
+                }
+            },
+        }
+    }
+}
+
+// Adds lo..=hi to the disjoint ranges in covered, mapping each start to its end, and returns the parts of it
+// that weren't covered yet
+fn cover(covered: &mut BTreeMap<isize, isize>, lo: isize, hi: isize) -> Vec<(isize, isize)> {
+    let first = covered.range(..=lo).next_back().filter(|&(_, &end)| end >= lo).map_or(lo, |(&start, _)| start);
+    let overlapping = covered.range(first..=hi).map(|(&start, &end)| (start, end)).collect::<Vec<(isize, isize)>>();
+    let mut gaps = Vec::new();
+    let (mut merged, mut next) = ((lo, hi), lo);
+    for (start, end) in overlapping {
+        if start > next {
+            gaps.push((next, start - 1));
+        }
+        next = next.max(end + 1);
+        merged = (merged.0.min(start), merged.1.max(end));
+        covered.remove(&start);
+    }
+    if next <= hi {
+        gaps.push((next, hi));
+    }
+    covered.insert(merged.0, merged.1);
+    gaps
+}
+
+#[test]
+fn test_cover() {
+    let mut covered = BTreeMap::new();
+    assert_eq!(cover(&mut covered, 2, 4), &[(2, 4)]);
+    assert_eq!(cover(&mut covered, 8, 9), &[(8, 9)]);
+    assert_eq!(cover(&mut covered, 3, 4), &[]);
+    assert_eq!(cover(&mut covered, 0, 10), &[(0, 1), (5, 7), (10, 10)]);
+    assert_eq!(covered.into_iter().collect::<Vec<(isize, isize)>>(), &[(0, 10)]);
+}
+
+// Segments along the same line share every point where their ranges overlap. A wire reaches every point of a
+// segment in no more steps than any later segment, so each wire's segments on a line are first cut down
+// to the points its earlier segments haven't covered, leaving disjoint runs that don't revisit any point
+fn collinear_crossings<F>(segments: &[Segment], axes: &[Axis], mut found: F)
+    where F: FnMut(&Segment, &Segment, Position)
+{
+    let mut lines = HashMap::new();
+    for (s, axis) in segments.iter().zip(axes) {
+        lines.entry((axis.horizontal, axis.line)).or_insert_with(Vec::new).push((*axis, s));
+    }
+    for (_, mut line) in lines {
+        line.sort_unstable_by_key(|(_, s)| (s.wire, s.steps));
+        let mut runs = Vec::with_capacity(line.len());
+        let mut covered = BTreeMap::new();
+        for (i, &(axis, s)) in line.iter().enumerate() {
+            if i > 0 && line[i - 1].1.wire != s.wire {
+                covered.clear();
+            }
+            runs.extend(cover(&mut covered, axis.lo, axis.hi).into_iter().map(|(lo, hi)| (Axis {lo, hi, ..axis}, s)));
+        }
+
+        // With each wire's runs disjoint, at most one run of every wire is active
+        runs.sort_unstable_by_key(|(axis, _)| axis.lo);
+        let mut active: Vec<(Axis, &Segment)> = Vec::new();
+        for (axis, s) in runs {
+            active.retain(|(a, _)| a.hi >= axis.lo);
+            for &(a, other) in active.iter().filter(|(_, other)| other.wire != s.wire) {
+                for point in (axis.lo..=a.hi.min(axis.hi)).filter_map(|t| axis.point(t)) {
+                    found(other, s, point);
+                }
+            }
+            active.push((axis, s));
+        }
+    }
+}
+
+// Steps k such that unit * k == offset, if offset lies on the line through the origin along unit
+fn steps_along(offset: Position, unit: Position) -> Option<isize> {
+    let k = [(offset.x, unit.x), (offset.y, unit.y), (offset.z, unit.z)].iter()
+        .find(|&&(_, u)| u != 0)
+        .map(|&(o, u)| o * u)?;
+    if unit * k == offset { Some(k) } else { None }
+}
+
+// Points shared by two segments going in any directions, from start_a + unit_a * s == start_b + unit_b * t
+fn segment_crossings<F: FnMut(Position)>(a: &Segment, b: &Segment, mut found: F) {
+    let offset = b.start - a.start;
+    if b.unit == a.unit || b.unit == a.unit * -1 {
+        // Parallel segments share a run of points if they're on the same line
+        if let Some(k) = steps_along(offset, a.unit) {
+            let end = if b.unit == a.unit { k + b.length } else { k - b.length };
+            for s in k.min(end).max(0)..=k.max(end).min(a.length) {
+                found(a.point(s));
+            }
+        }
+        return;
+    }
+
+    // Otherwise solve for s and t with two coordinates where the segments aren't parallel, then check the third
+    let (ua, ub, d) = ([a.unit.x, a.unit.y, a.unit.z], [b.unit.x, b.unit.y, b.unit.z], [offset.x, offset.y, offset.z]);
+    if let Some((i, j, det)) = [(0, 1), (0, 2), (1, 2)].iter()
+        .map(|&(i, j)| (i, j, ub[i] * ua[j] - ua[i] * ub[j]))
+        .find(|&(_, _, det)| det != 0)
+    {
+        let (s, t) = (ub[i] * d[j] - d[i] * ub[j], ua[i] * d[j] - ua[j] * d[i]);
+        // Diagonals can cross between points of the grid, which doesn't count
+        if s % det == 0 && t % det == 0 {
+            let (s, t) = (s / det, t / det);
+            if (0..=a.length).contains(&s) && (0..=b.length).contains(&t) && a.point(s) == b.point(t) {
+                found(a.point(s));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_segment_crossings() {
+    let crossings = |a: &str, b: &str| {
+        let a = wire_segments(&parse_wire(a).unwrap(), 0)[0];
+        let b = Segment {start: Position::new(4, 0, 0), ..wire_segments(&parse_wire(b).unwrap(), 1)[0]};
+        let mut points = vec![];
+        segment_crossings(&a, &b, |p| points.push(p));
+        points
+    };
+    assert_eq!(crossings("UR6", "UL6"), &[Position::new(2, 2, 0)]);
+    assert_eq!(crossings("UR5", "UL5"), &[Position::new(2, 2, 0)]);
+    assert_eq!(crossings("UR6", "UL1"), &[]);
+    assert_eq!(crossings("R8", "UL1"), &[Position::new(4, 0, 0)]);
+    assert_eq!(crossings("UR3", "U9"), &[]);
+    assert_eq!(crossings("UR5", "U9"), &[Position::new(4, 4, 0)]);
+    assert_eq!(crossings("R6", "L2"), &[Position::new(2, 0, 0), Position::new(3, 0, 0), Position::new(4, 0, 0)]);
+    assert_eq!(crossings("R6", "R9"), &[Position::new(4, 0, 0), Position::new(5, 0, 0), Position::new(6, 0, 0)]);
+    assert_eq!(crossings("L6", "R9"), &[]);
+    assert_eq!(crossings("R9", "F2"), &[Position::new(4, 0, 0)]);
+    assert_eq!(crossings("F9", "L4"), &[Position::ORIGIN]);
+    assert_eq!(crossings("F9", "UL4"), &[]);
+    // Odd offsets put crossing diagonals between points
+    let a = wire_segments(&parse_wire("UR6").unwrap(), 0)[0];
+    let b = Segment {start: Position::new(3, 0, 0), ..wire_segments(&parse_wire("UL6").unwrap(), 1)[0]};
+    segment_crossings(&a, &b, |p| panic!("Unexpected crossing at {}", p));
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Crossing {
+    point: Position,
+    steps: Vec<(usize, usize)>, // Fewest steps along each wire through point, as (wire, steps) ordered by wire
+}
+
+impl Crossing {
+    #[cfg(test)]
+    fn steps(&self, wire: usize) -> Option<usize> {
+        self.steps.iter().find(|&&(w, _)| w == wire).map(|&(_, steps)| steps)
+    }
+
+    // Combined signal delay of all the wires that meet here
+    fn delay(&self) -> usize {
+        self.steps.iter().map(|&(_, steps)| steps).sum()
+    }
+}
+
+// Every point other than the origin that two or more wires pass through, ordered by point. Segments in the z = 0
+// plane are swept in each frame, but segments leaving the plane are still compared with every segment of the other
+// wires, which is quadratic when there are many of them
+fn find_crossings(wires: &[WireSlice]) -> Vec<Crossing> {
+    let segments = wires.iter().enumerate()
+        .flat_map(|(i, wire)| wire_segments(wire, i))
+        .collect::<Vec<Segment>>();
+
+    let mut crossings = HashMap::new();
+    let mut found = |a: &Segment, b: &Segment, point: Position| {
+        if point == Position::ORIGIN {
+            return;
+        }
+        let steps = crossings.entry(point).or_insert_with(BTreeMap::new);
+        for s in [a, b].iter() {
+            let fewest = steps.entry(s.wire).or_insert(usize::MAX);
+            *fewest = (*fewest).min(s.steps_to(point));
+        }
+    };
+    for (i, &frame) in FRAMES.iter().enumerate() {
+        let (swept, axes): (Vec<Segment>, Vec<Axis>) = segments.iter()
+            .filter_map(|&s| Some((s, s.axis(frame)?)))
+            .unzip();
+        perpendicular_crossings(&swept, &axes, &mut found);
+        // The first two frames have every direction once, so runs along the same line are found there
+        if i < 2 {
+            collinear_crossings(&swept, &axes, &mut found);
+        }
+    }
+
+    // Each segment out of the plane is compared with the segments in it and any later segments out of it
+    for (i, a) in segments.iter().enumerate().filter(|(_, a)| !a.in_plane()) {
+        for (_, b) in segments.iter().enumerate().filter(|&(j, b)| b.wire != a.wire && (b.in_plane() || j > i)) {
+            segment_crossings(a, b, |point| found(a, b, point));
         }
     }
+
+    let mut crossings = crossings.into_iter()
+        .map(|(point, steps)| Crossing {point, steps: steps.into_iter().collect()})
+        .collect::<Vec<Crossing>>();
+    crossings.sort_unstable_by_key(|c| c.point);
+    crossings
+}
+
+// Walk every wire one point at a time, which is simple but only practical for short wires
+#[cfg(test)]
+fn find_crossings_naive(wires: &[WireSlice]) -> Vec<Crossing> {
+    let mut visited = HashMap::new();
+    for (i, wire) in wires.iter().enumerate() {
+        let mut position = Position::ORIGIN;
+        let mut steps = 0;
+        for &cardinal in wire.iter() {
+            for _ in 0..cardinal.distance() {
+                position = position + cardinal.unit();
+                steps += 1;
+                visited.entry(position).or_insert_with(BTreeMap::new).entry(i).or_insert(steps);
+            }
+        }
+    }
+    let mut crossings = visited.into_iter()
+        .filter(|(point, steps)| *point != Position::ORIGIN && steps.len() > 1)
+        .map(|(point, steps)| Crossing {point, steps: steps.into_iter().collect()})
+        .collect::<Vec<Crossing>>();
+    crossings.sort_unstable_by_key(|c| c.point);
+    crossings
+}
+
+#[cfg(test)]
+fn find_crossings_helper(wires: &[&str]) -> Vec<Crossing> {
+    let wires = wires.iter().map(|w| parse_wire(w).unwrap()).collect::<Vec<Wire>>();
+    find_crossings(&wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>())
 }
 
 #[test]
-fn test_intersect_lines() {
-    assert_eq!(intersect_lines(Coordinate(2, 0), Cardinal::Up(4), Coordinate(0, 2), Cardinal::Right(4)), Some(Coordinate(2, 2)));
-    assert_eq!(intersect_lines(Coordinate(4, 2), Cardinal::Left(4), Coordinate(2, 4), Cardinal::Down(4)), Some(Coordinate(2, 2)));
-    assert_eq!(intersect_lines(Coordinate(0, 2), Cardinal::Right(2), Coordinate(4, 2), Cardinal::Left(2)), Some(Coordinate(2, 2)));
-    assert_eq!(intersect_lines(Coordinate(0, 2), Cardinal::Right(3), Coordinate(4, 0), Cardinal::Up(4)), None);
-}
-
-fn find_intersection(first: WireSlice, second: WireSlice) -> Option<(u32, u32)> {
-    let mut closest: Option<Coordinate> = None;
-    let mut shortest: Option<u32> = None;
-
-    let mut origin1 = Coordinate(0, 0);
-    let mut distance1 = 0;
-    for &cardinal1 in first {
-        let mut origin2 = Coordinate(0, 0);
-        let mut distance2 = 0;
-        for &cardinal2 in second {
-            if let Some(point) = intersect_lines(origin1, cardinal1, origin2, cardinal2) {
-                // Ignore intersections at the origin
-                if point != Coordinate(0, 0) {
-                    closest = Some(match closest {
-                        Some(old) => if point.distance() < old.distance() { point } else { old },
-                        None => point,
-                    });
-                    // Distance to start of each segment PLUS distance from each segment to intersection
-                    let distance = distance1 + distance2 + (point - origin1).distance() + (point - origin2).distance();
-                    shortest = Some(match shortest {
-                        Some(old) => if distance < old { distance } else { old },
-                        None => distance,
-                    });
+fn test_find_crossings() {
+    assert_eq!(find_crossings_helper(&["R8,U5,L5,D3", "U7,R6,D4,L4"]), &[
+        Crossing {point: Position::new(3, 3, 0), steps: vec![(0, 20), (1, 20)]},
+        Crossing {point: Position::new(6, 5, 0), steps: vec![(0, 15), (1, 15)]}]);
+
+    // Overlapping runs share every point, including where both wires leave the origin together
+    let points = find_crossings_helper(&["R10", "R3,U1,R2,D1,L1,R4"]).iter()
+        .map(|c| (c.point.x, c.steps(0).unwrap(), c.steps(1).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(points, &[(1, 1, 1), (2, 2, 2), (3, 3, 3), (4, 4, 8), (5, 5, 7), (6, 6, 10), (7, 7, 11), (8, 8, 12)]);
+
+    // Touching end to end, and a wire that doubles back over itself
+    assert_eq!(find_crossings_helper(&["U3", "R1,U3,L1,U2,D4"]), &[
+        Crossing {point: Position::new(0, 1, 0), steps: vec![(0, 1), (1, 11)]},
+        Crossing {point: Position::new(0, 2, 0), steps: vec![(0, 2), (1, 10)]},
+        Crossing {point: Position::new(0, 3, 0), steps: vec![(0, 3), (1, 5)]}]);
+
+    // Wires that keep doubling back over the same points, which would otherwise compare every pair of their segments
+    let first = vec!["R1,L1"; 20_000].join(",");
+    let second = vec!["L1,R2,L1"; 20_000].join(",");
+    assert_eq!(find_crossings_helper(&[&first, &second]), &[
+        Crossing {point: Position::new(1, 0, 0), steps: vec![(0, 1), (1, 3)]}]);
+    let first = vec!["UR1,DL1"; 20_000].join(",");
+    let second = vec!["DL1,UR2,DL1"; 20_000].join(",");
+    assert_eq!(find_crossings_helper(&[&first, &second]), &[
+        Crossing {point: Position::new(1, 1, 0), steps: vec![(0, 1), (1, 3)]}]);
+
+    assert_eq!(find_crossings_helper(&["L2", "R2"]), &[]);
+    assert_eq!(find_crossings_helper(&["R2"]), &[]);
+
+    // Three wires, all meeting at 2,0
+    let crossings = find_crossings_helper(&["R4", "U1,R2,D2", "U2,R2,D4"]);
+    assert_eq!(crossings, &[
+        Crossing {point: Position::new(0, 1, 0), steps: vec![(1, 1), (2, 1)]},
+        Crossing {point: Position::new(2, -1, 0), steps: vec![(1, 5), (2, 7)]},
+        Crossing {point: Position::new(2, 0, 0), steps: vec![(0, 2), (1, 4), (2, 6)]},
+        Crossing {point: Position::new(2, 1, 0), steps: vec![(1, 3), (2, 5)]}]);
+    assert_eq!(crossings[2].delay(), 12);
+    assert_eq!(crossings[2].steps(1), Some(4));
+    assert_eq!(crossings[0].steps(0), None);
+}
+
+#[test]
+fn test_find_crossings_naive() {
+    for seed in 1..=20 {
+        let wires = (0..3).map(|i| generate_wire(300, 12, &DIRECTIONS[..4], seed + i * 100)).collect::<Vec<Wire>>();
+        let wires = wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>();
+        assert_eq!(find_crossings(&wires), find_crossings_naive(&wires));
+    }
+
+    // Diagonals are swept with each other and with orthogonal segments
+    for seed in 1..=20 {
+        let wires = (0..3).map(|i| generate_wire(300, 12, &DIRECTIONS[..8], seed + i * 100)).collect::<Vec<Wire>>();
+        let wires = wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>();
+        assert_eq!(find_crossings(&wires), find_crossings_naive(&wires));
+    }
+
+    // 3D segments are compared segment by segment, alongside the sweep of the rest
+    for seed in 1..=10 {
+        let wires = (0..3).map(|i| generate_wire(100, 6, &DIRECTIONS, seed + i * 100)).collect::<Vec<Wire>>();
+        let wires = wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>();
+        let crossings = find_crossings(&wires);
+        assert!(!crossings.is_empty());
+        assert_eq!(crossings, find_crossings_naive(&wires));
+    }
+
+    // A single diagonal leaves the long runs of the rest to the sweep
+    let wires = [
+        format!("UR1,{}", vec!["R1,L1"; 20_000].join(",")),
+        format!("U1,R1,{}", vec!["L1,R2,L1"; 20_000].join(","))];
+    let wires = wires.iter().map(|w| parse_wire(w).unwrap()).collect::<Vec<Wire>>();
+    let wires = wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>();
+    assert_eq!(find_crossings(&wires), find_crossings_naive(&wires));
+    assert_eq!(find_crossings(&wires).len(), 2);
+}
+
+// The crossings of wires a and b closest to the origin and with the fewest combined steps along a and b
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct PairReport {
+    wires: (usize, usize),
+    closest: Option<(Position, usize)>,
+    fastest: Option<(Position, usize)>,
+}
+
+// One report for each pair of the given number of wires, in order
+fn pair_reports(crossings: &[Crossing], count: usize, metric: Metric) -> Vec<PairReport> {
+    let mut reports = (0..count)
+        .flat_map(|a| (a + 1..count).map(move |b| ((a, b), PairReport {wires: (a, b), closest: None, fastest: None})))
+        .collect::<BTreeMap<(usize, usize), PairReport>>();
+    let better = |old: Option<(Position, usize)>, new: (Position, usize)| match old {
+        Some(old) if old.1 <= new.1 => Some(old),
+        _ => Some(new),
+    };
+    for crossing in crossings {
+        for (i, &(a, steps_a)) in crossing.steps.iter().enumerate() {
+            for &(b, steps_b) in crossing.steps[i + 1..].iter() {
+                if let Some(report) = reports.get_mut(&(a, b)) {
+                    report.closest = better(report.closest, (crossing.point, metric.distance(crossing.point)));
+                    report.fastest = better(report.fastest, (crossing.point, steps_a + steps_b));
                 }
             }
-            origin2 += cardinal2;
-            distance2 += cardinal2.distance();
         }
-        origin1 += cardinal1;
-        distance1 += cardinal1.distance();
     }
+    reports.into_values().collect()
+}
+
+#[test]
+fn test_pair_reports() {
+    let crossings = find_crossings_helper(&["R4", "U1,R2,D2", "U2,R2,D4", "L1"]);
+    assert_eq!(pair_reports(&crossings, 4, Metric::Manhattan), &[
+        PairReport {wires: (0, 1), closest: Some((Position::new(2, 0, 0), 2)), fastest: Some((Position::new(2, 0, 0), 6))},
+        PairReport {wires: (0, 2), closest: Some((Position::new(2, 0, 0), 2)), fastest: Some((Position::new(2, 0, 0), 8))},
+        PairReport {wires: (0, 3), closest: None, fastest: None},
+        PairReport {wires: (1, 2), closest: Some((Position::new(0, 1, 0), 1)), fastest: Some((Position::new(0, 1, 0), 2))},
+        PairReport {wires: (1, 3), closest: None, fastest: None},
+        PairReport {wires: (2, 3), closest: None, fastest: None}]);
 
-    closest.map(|c| (Coordinate::distance(c), shortest.unwrap()))
+    // A diagonal move counts as one step, and as one unit of distance with the Chebyshev metric
+    let crossings = find_crossings_helper(&["UR3", "R3,U5"]);
+    let at = Position::new(3, 3, 0);
+    assert_eq!(pair_reports(&crossings, 2, Metric::Manhattan)[0].closest, Some((at, 6)));
+    assert_eq!(pair_reports(&crossings, 2, Metric::Chebyshev)[0].closest, Some((at, 3)));
+    assert_eq!(pair_reports(&crossings, 2, Metric::Chebyshev)[0].fastest, Some((at, 9)));
+    assert!(Metric::parse("euclidean").is_err());
+}
+
+fn format_reports(reports: &[PairReport]) -> String {
+    let cell = |found: Option<(Position, usize)>| match found {
+        Some((point, value)) => format!("{} at {}", value, point),
+        None => "-".to_string(),
+    };
+    let mut table = format!("{:<8}{:<24}{}", "Wires", "Closest", "Fewest steps");
+    for report in reports {
+        let wires = format!("{}-{}", report.wires.0 + 1, report.wires.1 + 1);
+        table += &format!("\n{:<8}{:<24}{}", wires, cell(report.closest), cell(report.fastest));
+    }
+    table
+}
+
+#[test]
+fn test_format_reports() {
+    let crossings = find_crossings_helper(&["R4", "U1,R2,D2", "L1"]);
+    assert_eq!(format_reports(&pair_reports(&crossings, 3, Metric::Manhattan)).lines().collect::<Vec<&str>>(), &[
+        "Wires   Closest                 Fewest steps",
+        "1-2     2 at 2,0                6 at 2,0",
+        "1-3     -                       -",
+        "2-3     -                       -"]);
 }
 
+// The points where at least k wires meet, by combined signal delay
+fn format_meeting(crossings: &[Crossing], k: usize) -> String {
+    let mut meeting = crossings.iter().filter(|c| c.steps.len() >= k).collect::<Vec<&Crossing>>();
+    meeting.sort_by_key(|c| (c.delay(), c.point));
+    let mut list = format!("{} points where {} or more wires meet", meeting.len(), k);
+    for crossing in meeting {
+        list += &format!("\n{}: {} wires, delay {}", crossing.point, crossing.steps.len(), crossing.delay());
+    }
+    list
+}
+
+#[test]
+fn test_format_meeting() {
+    let crossings = find_crossings_helper(&["F2,R3", "R3,F2", "F2,UR1"]);
+    assert_eq!(format_meeting(&crossings, 2).lines().collect::<Vec<&str>>(), &[
+        "3 points where 2 or more wires meet",
+        "0,0,1: 2 wires, delay 2",
+        "0,0,2: 2 wires, delay 4",
+        "3,0,2: 2 wires, delay 10"]);
+    assert_eq!(format_meeting(&crossings, 3), "0 points where 3 or more wires meet");
+}
+
+// Distance from the origin to the closest crossing, and the fewest combined steps to a crossing
 #[cfg(test)]
-fn find_intersection_helper(first: &str, second: &str, result: (u32, u32)) {
+fn find_intersection(first: WireSlice, second: WireSlice) -> Option<(usize, usize)> {
+    let report = pair_reports(&find_crossings(&[first, second]), 2, Metric::Manhattan)[0];
+    Some((report.closest?.1, report.fastest?.1))
+}
+
+#[cfg(test)]
+fn find_intersection_helper(first: &str, second: &str, result: (usize, usize)) {
     let first = parse_wire(first).unwrap();
     let second = parse_wire(second).unwrap();
     assert_eq!(find_intersection(&first, &second), Some(result));
@@ -247,19 +851,229 @@ fn test_find_intersection() {
         (135, 410));
 }
 
-fn main() -> SuperResult<()> {
-    let reader = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        BufReader::new(File::open(name.as_ref())?)
+// Corners of the wire's path, starting from the origin
+fn wire_vertices(wire: WireSlice) -> Vec<Position> {
+    let mut vertices = vec![Position::ORIGIN];
+    vertices.extend(wire.iter().scan(Position::ORIGIN, |position, &cardinal| {
+        *position += cardinal;
+        Some(*position)
+    }));
+    vertices
+}
+
+// Crossings worth pointing out: the closest to the origin and the one with the fewest combined steps over all pairs
+fn highlights(reports: &[PairReport]) -> (Option<Position>, Option<Position>) {
+    let best = |found: fn(&PairReport) -> Option<(Position, usize)>| reports.iter()
+        .filter_map(found)
+        .min_by_key(|&(point, value)| (value, point))
+        .map(|(point, _)| point);
+    (best(|r| r.closest), best(|r| r.fastest))
+}
+
+// Draw the wires on the x-y plane within columns by rows characters, shrinking the layout when it doesn't fit; up is
+// up, unlike the screen coordinates of the grid. Moves along z show up as #. Crossings are X, the closest C and
+// the fewest steps S, or * when one is both
+fn render_ascii(wires: &[WireSlice], crossings: &[Crossing], highlight: (Option<Position>, Option<Position>),
+    columns: usize, rows: usize) -> String
+{
+    let vertices = wires.iter().map(|wire| wire_vertices(wire)).collect::<Vec<Vec<Position>>>();
+    let bounds = Bounds::from_points(vertices.iter().flatten().map(|p| p.plane())).unwrap_or_else(|| Bounds::new(Point::ORIGIN));
+    let scale = bounds.width().div_ceil(columns.max(1)).max(bounds.height().div_ceil(rows.max(1))).max(1);
+    let cell = |p: Position| Point::new((p.x - bounds.min.x) / scale as isize, (bounds.max.y - p.y) / scale as isize);
+
+    let mut grid = Grid::new((bounds.width() - 1) / scale + 1, (bounds.height() - 1) / scale + 1, '.');
+    for (wire, vertices) in wires.iter().zip(vertices.iter()) {
+        for (&cardinal, pair) in wire.iter().zip(vertices.windows(2)) {
+            let glyph = match cardinal {
+                Cardinal::Up(_) | Cardinal::Down(_) => '|',
+                Cardinal::Left(_) | Cardinal::Right(_) => '-',
+                Cardinal::UpRight(_) | Cardinal::DownLeft(_) => '/',
+                Cardinal::UpLeft(_) | Cardinal::DownRight(_) => '\\',
+                Cardinal::Forward(_) | Cardinal::Backward(_) => '#',
+            };
+            let (start, end) = (cell(pair[0]), cell(pair[1]));
+            let mut p = start;
+            grid[p] = glyph;
+            while p != end {
+                p = Point::new(p.x + (end.x - p.x).signum(), p.y + (end.y - p.y).signum());
+                grid[p] = glyph;
+            }
+        }
+        for &turn in vertices.iter().skip(1).take(vertices.len().saturating_sub(2)) {
+            grid[cell(turn)] = '+';
+        }
+    }
+    for crossing in crossings {
+        grid[cell(crossing.point)] = 'X';
+    }
+    match highlight {
+        (Some(closest), Some(fastest)) if closest == fastest => grid[cell(closest)] = '*',
+        (closest, fastest) => {
+            closest.into_iter().for_each(|p| grid[cell(p)] = 'C');
+            fastest.into_iter().for_each(|p| grid[cell(p)] = 'S');
+        }
+    }
+    grid[cell(Position::ORIGIN)] = 'o';
+    grid.render(|&c| c)
+}
+
+#[test]
+fn test_render_ascii() {
+    let first = parse_wire("R8,U5,L5,D3").unwrap();
+    let second = parse_wire("U7,R6,D4,L4").unwrap();
+    let wires = [first.as_slice(), second.as_slice()];
+    let crossings = find_crossings(&wires);
+    let highlight = highlights(&pair_reports(&crossings, 2, Metric::Manhattan));
+    assert_eq!(highlight, (Some(Position::new(3, 3, 0)), Some(Position::new(6, 5, 0))));
+
+    assert_eq!(render_ascii(&wires, &crossings, highlight, 80, 40).lines().collect::<Vec<&str>>(), &[
+        "+-----+..",
+        "|.....|..",
+        "|..+--S-+",
+        "|..|..|.|",
+        "|.-C--+.|",
+        "|..|....|",
+        "|.......|",
+        "o-------+"]);
+
+    // Twice as large in each direction as fits, so every two points share a character
+    let small = render_ascii(&wires, &crossings, highlight, 5, 4);
+    assert_eq!(small.lines().collect::<Vec<&str>>(), &["+--+.", "|+-S+", "|C-+|", "o---+"]);
+    assert_eq!(render_ascii(&[], &[], (None, None), 10, 10), "o");
+}
+
+const WIRE_COLORS: [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e", "#17becf"];
+
+// Wires on the x-y plane as polylines in a view box around them, flipped so that up is up and drawn about 800 pixels across
+fn render_svg(wires: &[WireSlice], crossings: &[Crossing], highlight: (Option<Position>, Option<Position>)) -> String {
+    let vertices = wires.iter().map(|wire| wire_vertices(wire)).collect::<Vec<Vec<Position>>>();
+    let bounds = Bounds::from_points(vertices.iter().flatten().map(|p| p.plane())).unwrap_or_else(|| Bounds::new(Point::ORIGIN));
+    let size = bounds.width().max(bounds.height()) as f64;
+    let margin = size / 20.0;
+    let stroke = size / 400.0;
+    let width = (bounds.width() - 1) as f64 + 2.0 * margin;
+    let height = (bounds.height() - 1) as f64 + 2.0 * margin;
+    let pixels = 800.0 / width.max(height);
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">\n",
+        width * pixels, height * pixels, bounds.min.x as f64 - margin, -bounds.max.y as f64 - margin, width, height);
+    for (i, wire) in vertices.iter().enumerate() {
+        let points = wire.iter().map(|p| format!("{},{}", p.x, -p.y)).collect::<Vec<String>>().join(" ");
+        svg += &format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{:.3}\" stroke-linejoin=\"round\"/>\n",
+            points, WIRE_COLORS[i % WIRE_COLORS.len()], stroke);
+    }
+    let mut circle = |p: Position, r: f64, style: String| {
+        svg += &format!("<circle cx=\"{}\" cy=\"{}\" r=\"{:.3}\" {}/>\n", p.x, -p.y, r, style);
     };
+    for crossing in crossings {
+        circle(crossing.point, stroke * 2.0, "fill=\"black\"".to_string());
+    }
+    let ring = |color| format!("fill=\"none\" stroke=\"{}\" stroke-width=\"{:.3}\"", color, stroke);
+    if let Some(closest) = highlight.0 {
+        circle(closest, stroke * 6.0, ring("#e6a100"));
+    }
+    if let Some(fastest) = highlight.1 {
+        circle(fastest, stroke * 9.0, ring("#00a0e6"));
+    }
+    circle(Position::ORIGIN, stroke * 4.0, "fill=\"black\"".to_string());
+    svg + "</svg>\n"
+}
+
+#[test]
+fn test_render_svg() {
+    let first = parse_wire("R8,U5,L5,D3").unwrap();
+    let second = parse_wire("U7,R6,D4,L4").unwrap();
+    let wires = [first.as_slice(), second.as_slice()];
+    let crossings = find_crossings(&wires);
+    let svg = render_svg(&wires, &crossings, highlights(&pair_reports(&crossings, 2, Metric::Manhattan)));
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"800\" height=\"710\" viewBox=\"-0.45 -7.45 8.90 7.90\">"));
+    assert!(svg.contains("<polyline points=\"0,0 8,0 8,-5 3,-5 3,-2\" fill=\"none\" stroke=\"#1f77b4\""));
+    assert!(svg.contains("<polyline points=\"0,0 0,-7 6,-7 6,-3 2,-3\" fill=\"none\" stroke=\"#d62728\""));
+    assert_eq!(svg.matches("<circle").count(), 5);
+    assert!(svg.contains("<circle cx=\"3\" cy=\"-3\" r=\"0.135\" fill=\"none\" stroke=\"#e6a100\""));
+    assert!(svg.ends_with("</svg>\n"));
+}
+
+// Random walk of the given number of segments in the given directions, each up to max_length long
+fn generate_wire(segments: usize, max_length: u32, directions: &[fn(u32) -> Cardinal], seed: u64) -> Wire {
+    let mut rng = Xorshift::new(seed);
+    (0..segments)
+        .map(|_| {
+            let length = rng.below(max_length as u64) as u32 + 1;
+            directions[rng.below(directions.len() as u64) as usize](length)
+        })
+        .collect()
+}
+
+#[test]
+fn test_generate_wire() {
+    // Moves stay in the x-y plane along the axes, and every length and direction shows up
+    let wire = generate_wire(200, 5, &DIRECTIONS[..4], 3);
+    assert_eq!(wire.len(), 200);
+    assert!(wire.iter().all(|c| (1..=5).contains(&c.distance())));
+    assert!(wire.iter().all(|c| c.unit().z == 0 && (c.unit().x == 0 || c.unit().y == 0)));
+    assert!((1..=5).all(|length| wire.iter().any(|c| c.distance() == length)));
+    assert!(DIRECTIONS[..4].iter().all(|d| wire.iter().any(|c| c.unit() == d(1).unit())));
+    assert!(generate_wire(100, 5, &DIRECTIONS, 3).iter().any(|c| c.unit().z != 0));
+}
+
+// Moves the generated wires take: orthogonal or diagonal ones stay in the z = 0 plane and are swept, while 3d
+// ones are compared pairwise, which is quadratic in the number of segments leaving the plane
+fn bench_crossings(segments: usize, moves: &str) -> SuperResult<()> {
+    let directions = match moves {
+        "" | "orthogonal" => &DIRECTIONS[..4],
+        "diagonal" => &DIRECTIONS[..8],
+        "3d" => &DIRECTIONS[..],
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown moves {}", moves)).into()),
+    };
+    let first = generate_wire(segments, 1000, directions, 1);
+    let second = generate_wire(segments, 1000, directions, 2);
+    let start = Instant::now();
+    let crossings = find_crossings(&[&first, &second]);
+    let elapsed = start.elapsed();
+    println!("Wires of {} segments: {} crossings in {:.3?}", segments, crossings.len(), elapsed);
+    Ok(())
+}
+
+fn main() -> SuperResult<()> {
+    let args = Args::from_env();
+
+    // Time the crossings of two generated wires instead, e.g. --bench=100000 --moves=diagonal
+    if let Some(segments) = args.flag("bench") {
+        let segments = if segments.is_empty() { 100_000 } else { segments.parse()? };
+        return bench_crossings(segments, args.flag("moves").unwrap_or(""));
+    }
+
+    let reader = BufReader::new(File::open(args.input())?);
 
     // Collect lines of input into Vec<Vec<Cardinal>>
     let wires = reader.lines()
         .map(|s| parse_wire(&s?))
         .collect::<SuperResult<Vec<Wire>>>()?;
 
-    println!("{:?}", find_intersection(&wires[0], &wires[1]));
+    let wires = wires.iter().map(Vec::as_slice).collect::<Vec<WireSlice>>();
+    let crossings = find_crossings(&wires);
+    // Crossings are as close as their Manhattan distance from the origin, or --metric=chebyshev
+    let metric = args.flag("metric").map(Metric::parse).transpose()?.unwrap_or(Metric::Manhattan);
+    let reports = pair_reports(&crossings, wires.len(), metric);
+    println!("{}", format_reports(&reports));
+
+    // Draw the wires in the terminal, at most so many columns wide, e.g. --ascii=120
+    if let Some(columns) = args.flag("ascii") {
+        let columns = if columns.is_empty() { 80 } else { columns.parse()? };
+        println!("\n{}", render_ascii(&wires, &crossings, highlights(&reports), columns, columns / 2));
+    }
+
+    // Save the wires as a drawing, e.g. --svg=wires.svg
+    if let Some(path) = args.flag("svg") {
+        fs::write(path, render_svg(&wires, &crossings, highlights(&reports)))?;
+    }
+
+    // List the points where at least k wires meet by combined signal delay, e.g. --meet=3
+    if let Some(k) = args.flag("meet") {
+        let k = if k.is_empty() { wires.len() } else { k.parse()? };
+        println!("\n{}", format_meeting(&crossings, k));
+    }
 
     Ok(())
 }
@@ -1,30 +1,449 @@
-use std::env;
+#[macro_use]
+extern crate quick_error;
+
+use aoc2019::cli::Args;
+
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::iter;
+use std::num::ParseIntError;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum SuperError {
+        IoError(err: io::Error) { from() }
+        ParseIntError(err: ParseIntError) { from() }
+    }
+}
+
+type SuperResult<T> = Result<T, SuperError>;
+
+fn main() -> SuperResult<()> {
+    let args = Args::from_env();
+    // The range is either given directly, e.g. 123456-654321, or read from the input file
+    let (start, end) = match args.get(0) {
+        Some(arg) if is_range(arg) => parse_range(arg)?,
+        _ => parse_range(&fs::read_to_string(args.input())?)?,
+    };
+    let ranges = split_range(&start, &end);
+
+    // Visit every candidate rather than counting them digit by digit, e.g. --enumerate
+    let enumerate = args.flag("enumerate").is_some();
+
+    // Count passwords following a custom policy instead, e.g. --rules=nondecreasing,run>=3,forbid=0
+    let policies = match args.flag("rules") {
+        Some(rules) => vec![parse_policy(rules)?],
+        None => vec![vec![Rule::NonDecreasing, Rule::RunAtLeast(2)], vec![Rule::NonDecreasing, Rule::RunExactly(2)]],
+    };
+
+    // Print the passwords following the last policy, e.g. --list
+    if args.flag("list").is_some() {
+        let policy = &policies[policies.len() - 1];
+        for (start, end) in ranges.iter() {
+            for sequence in matching(policy, start, end) {
+                println!("{}", sequence.iter().map(|d| d.to_string()).collect::<String>());
+            }
+        }
+    }
+
+    let counts = policies.iter().map(|policy| count_ranges(policy, &ranges, enumerate)).collect::<io::Result<Vec<u128>>>()?;
+    println!("{}", counts.iter().map(|count| count.to_string()).collect::<Vec<String>>().join(" "));
+    Ok(())
+}
+
+// Whether an argument is shaped like a range rather than a path, whatever files exist
+fn is_range(arg: &str) -> bool {
+    let digits = |bound: &str| !bound.is_empty() && bound.chars().all(|c| c.is_ascii_digit());
+    arg.split_once('-').is_some_and(|(start, end)| digits(start) && digits(end))
+}
+
+#[test]
+fn test_is_range() {
+    assert!(is_range("123456-654321"));
+    assert!(is_range("7-0100"));
+    assert!(!is_range("inptu"));
+    assert!(!is_range("input/day4.txt"));
+    assert!(!is_range("123-"));
+    assert!(!is_range("1-2-3"));
+    assert!(!is_range("ranges/1-2"));
+}
+
+// A range like 123456-654321, whose bounds may have different lengths if neither has leading zeros
+fn parse_range(s: &str) -> io::Result<(Vec<u32>, Vec<u32>)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{} but got {:?}", message, s.trim()));
+    let digits = |bound: &str| match bound.trim() {
+        "" => None,
+        bound => bound.chars().map(|c| c.to_digit(10)).collect::<Option<Vec<u32>>>(),
+    };
+    let (start, end) = match s.trim().split_once('-').map(|(start, end)| (digits(start), digits(end))) {
+        Some((Some(start), Some(end))) => (start, end),
+        _ => return Err(invalid("Expected a range like 123456-654321")),
+    };
+    // Bounds of different lengths are split by length, so a leading zero would put numbers in the wrong piece
+    let padded = |bound: &[u32]| bound.len() > 1 && bound[0] == 0;
+    if start.len() != end.len() && (padded(&start) || padded(&end)) {
+        return Err(invalid("Expected no leading zeros in bounds of different lengths"));
+    }
+    if !is_sequence_le(&start, &end) {
+        return Err(invalid("Expected the start of the range to come before its end"));
+    }
+    Ok((start, end))
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range("123-4567\n").unwrap(), (vec![1, 2, 3], vec![4, 5, 6, 7]));
+    assert_eq!(parse_range("007-010").unwrap(), (vec![0, 0, 7], vec![0, 1, 0]));
+    assert!(parse_range("123456").is_err());
+    assert!(parse_range("123-").is_err());
+    assert!(parse_range("12a-456").is_err());
+    assert!(parse_range("1-2-3").is_err());
+    assert!(parse_range("456-123").is_err());
+    assert!(parse_range("1000-999").is_err());
+    assert!(parse_range("007-0100").is_err());
+    assert!(parse_range("07-100").is_err());
+    assert_eq!(parse_range("0-100").unwrap(), (vec![0], vec![1, 0, 0]));
+}
+
+// Split a range into ranges whose bounds have the same length, where lengths between those of the bounds have no leading zeros
+fn split_range(start: &[u32], end: &[u32]) -> Vec<(Vec<u32>, Vec<u32>)> {
+    if start.len() >= end.len() {
+        return vec![(start.to_vec(), end.to_vec())];
+    }
+    let lowest = |len: usize| (0..len).map(|i| (i == 0) as u32).collect::<Vec<u32>>();
+    let mut ranges = vec![(start.to_vec(), vec![9; start.len()])];
+    ranges.extend((start.len() + 1..end.len()).map(|len| (lowest(len), vec![9; len])));
+    ranges.push((lowest(end.len()), end.to_vec()));
+    ranges
+}
+
+#[test]
+fn test_split_range() {
+    assert_eq!(split_range(&[1, 2], &[3, 4]), vec![(vec![1, 2], vec![3, 4])]);
+    assert_eq!(split_range(&[9, 5], &[1, 0, 0, 4, 2]), vec![
+        (vec![9, 5], vec![9, 9]),
+        (vec![1, 0, 0], vec![9, 9, 9]),
+        (vec![1, 0, 0, 0], vec![9, 9, 9, 9]),
+        (vec![1, 0, 0, 0, 0], vec![1, 0, 0, 4, 2])]);
+
+    // Counting over the pieces agrees with checking every number in between
+    let policy = parse_policy("nondecreasing,run>=2").unwrap();
+    let count = split_range(&[7], &[2, 3, 4, 5]).iter()
+        .map(|(start, end)| DigitCounter::new(&policy, start, end).count().unwrap()).sum::<u128>();
+    let brute = (7..=2345u32)
+        .map(|n| n.to_string().chars().map(|c| c.to_digit(10).unwrap()).collect::<Vec<u32>>())
+        .filter(|digits| is_allowed(&policy, digits)).count();
+    assert_eq!(count, brute as u128);
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Rule {
+    NonDecreasing,
+    RunExactly(usize), // Some digit repeats exactly this many times in a row
+    RunAtLeast(usize),
+    DigitSum(u32, u32), // Inclusive bounds on the sum of the digits
+    Forbidden(u16), // Bit mask of digits that can't appear
+}
+
+impl Rule {
+    fn parse(s: &str) -> io::Result<Rule> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid rule {:?}", s));
+        let number = |n: &str| n.trim().parse::<u32>().map_err(|_| invalid());
+        let (name, value) = match s.find(['=', '>']) {
+            Some(i) => (&s[..i], &s[i..]),
+            None => (s, ""),
+        };
+        match (name.trim(), value) {
+            ("nondecreasing", "") => Ok(Rule::NonDecreasing),
+            ("run", value) if value.starts_with(">=") => Ok(Rule::RunAtLeast(number(&value[2..])? as usize)),
+            ("run", value) if value.starts_with('=') => Ok(Rule::RunExactly(number(&value[1..])? as usize)),
+            ("sum", value) if value.starts_with('=') => {
+                let value = &value[1..];
+                match value.split_once("..") {
+                    Some((lo, hi)) => match (number(lo)?, number(hi)?) {
+                        (lo, hi) if lo > hi => Err(invalid()),
+                        (lo, hi) => Ok(Rule::DigitSum(lo, hi)),
+                    },
+                    None => Ok(Rule::DigitSum(number(value)?, number(value)?)),
+                }
+            }
+            ("forbid", value) if value.starts_with('=') => value[1..].chars()
+                .try_fold(0, |mask, c| c.to_digit(10).map(|d| mask | 1 << d).ok_or_else(invalid))
+                .map(Rule::Forbidden),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn check(&self, sequence: &[u32]) -> bool {
+        match *self {
+            Rule::NonDecreasing => sequence.windows(2).all(|w| w[0] <= w[1]),
+            Rule::RunExactly(k) => runs(sequence).any(|run| run == k),
+            Rule::RunAtLeast(k) => runs(sequence).any(|run| run >= k),
+            Rule::DigitSum(lo, hi) => (lo..=hi).contains(&sequence.iter().sum()),
+            Rule::Forbidden(mask) => sequence.iter().all(|&d| mask & 1 << d == 0),
+        }
+    }
+}
+
+// DigitCounter keeps track of which rules are met in a u64
+const MAX_RULES: usize = 64;
+
+// Comma separated rules which must all hold
+fn parse_policy(s: &str) -> io::Result<Vec<Rule>> {
+    let policy = s.split(',').map(Rule::parse).collect::<io::Result<Vec<Rule>>>()?;
+    if policy.len() > MAX_RULES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("At most {} rules, not {}", MAX_RULES, policy.len())));
+    }
+    Ok(policy)
+}
+
+#[test]
+fn test_parse_policy() {
+    assert_eq!(parse_policy("nondecreasing,run=2,run>=3,sum=10..20,sum=7,forbid=13").unwrap(), vec![
+        Rule::NonDecreasing, Rule::RunExactly(2), Rule::RunAtLeast(3),
+        Rule::DigitSum(10, 20), Rule::DigitSum(7, 7), Rule::Forbidden(0b1010)]);
+    assert!(parse_policy("run").is_err());
+    assert!(parse_policy("sum=1..x").is_err());
+    assert!(parse_policy("sum=20..10").is_err());
+    assert!(parse_policy("forbid=a").is_err());
+    assert!(parse_policy("nondecreasing=1").is_err());
+    assert_eq!(parse_policy(&vec!["run>=2"; 64].join(",")).unwrap().len(), 64);
+    assert_eq!(parse_policy(&vec!["run>=2"; 65].join(",")).unwrap_err().to_string(), "At most 64 rules, not 65");
+}
+
+fn is_allowed(policy: &[Rule], sequence: &[u32]) -> bool {
+    policy.iter().all(|rule| rule.check(sequence))
+}
+
+// Lengths of the runs of equal digits
+fn runs(sequence: &[u32]) -> impl Iterator<Item = usize> + '_ {
+    let mut start = 0;
+    (1..=sequence.len()).filter_map(move |i| {
+        if i == sequence.len() || sequence[i] != sequence[i - 1] {
+            let run = i - start;
+            start = i;
+            Some(run)
+        } else {
+            None
+        }
+    })
+}
 
-fn main() {
-    let input = env::args().nth(1).expect("Expected argument ######-######")
-        .trim().split('-')
-        .map(|s| {
-            s.chars().map(|c| {
-                c.to_digit(10).unwrap_or_else(|| panic!("Invalid digit {}", c))
-            }).collect::<Vec<u32>>()
-        }).collect::<Vec<Vec<u32>>>();
-
-    let end = &input[1];
-    let mut current = first_monotonic_sequence(&input[0]);
-    let mut repeating_count = 0;
-    let mut doubled_count = 0;
-    while is_sequence_le(&current, end) {
-        if is_sequence_repeating(&current) {
-            repeating_count += 1;
+#[test]
+fn test_runs() {
+    assert_eq!(runs(&[1, 1, 2, 3, 3, 3]).collect::<Vec<usize>>(), vec![2, 1, 3]);
+    assert_eq!(runs(&[4]).collect::<Vec<usize>>(), vec![1]);
+    assert_eq!(runs(&[]).count(), 0);
+}
+
+// Sequences from start to end inclusive, of the same length, that follow every rule, in order
+fn matching<'a>(policy: &'a [Rule], start: &[u32], end: &'a [u32]) -> impl Iterator<Item = Vec<u32>> + 'a {
+    // Only non-decreasing sequences can match, so skip straight between them
+    let monotonic = policy.contains(&Rule::NonDecreasing);
+    let mut current = Some(if monotonic { first_monotonic_sequence(start) } else { start.to_vec() });
+    iter::from_fn(move || {
+        while let Some(sequence) = current.take() {
+            if !is_sequence_le(&sequence, end) {
+                return None;
+            }
+            let mut next = sequence.clone();
+            let more = if monotonic { increment_monotonic_sequence(&mut next) } else { increment_sequence(&mut next) };
+            if more {
+                current = Some(next);
+            }
+            if is_allowed(policy, &sequence) {
+                return Some(sequence);
+            }
         }
-        if is_sequence_doubled(&current) {
-            doubled_count += 1;
+        None
+    })
+}
+
+fn count_matching(policy: &[Rule], start: &[u32], end: &[u32]) -> usize {
+    matching(policy, start, end).count()
+}
+
+#[test]
+fn test_count_matching() {
+    let start = [1, 2, 3, 2, 5, 7];
+    let end = [6, 5, 3, 2, 1, 0];
+    let policy = parse_policy("nondecreasing,run>=2").unwrap();
+    let brute = |policy: &[Rule]| (123257..=653210u32)
+        .map(|n| n.to_string().chars().map(|c| c.to_digit(10).unwrap()).collect::<Vec<u32>>())
+        .filter(|digits| is_allowed(policy, digits)).count();
+    assert_eq!(count_matching(&policy, &start, &end), brute(&policy));
+    let policy = parse_policy("nondecreasing,run=2,sum=20..30").unwrap();
+    assert_eq!(count_matching(&policy, &start, &end), brute(&policy));
+    let policy = parse_policy("run=3,forbid=05").unwrap();
+    assert_eq!(count_matching(&policy, &start, &end), brute(&policy));
+
+    // Stops rather than wrapping around past the last sequence
+    assert_eq!(count_matching(&[Rule::NonDecreasing], &[9, 9, 8], &[9, 9, 9]), 1);
+    assert_eq!(count_matching(&[], &[9, 9, 8], &[9, 9, 9]), 2);
+    assert_eq!(matching(&[Rule::NonDecreasing, Rule::RunAtLeast(3)], &[5, 5, 5, 0], &[5, 5, 6, 0]).collect::<Vec<Vec<u32>>>(),
+        vec![vec![5, 5, 5, 5], vec![5, 5, 5, 6], vec![5, 5, 5, 7], vec![5, 5, 5, 8], vec![5, 5, 5, 9]]);
+}
+
+// What the rules need to know about a prefix of a sequence, so that prefixes with the same state are only counted once
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct DigitState {
+    position: usize,
+    prev: Option<u32>,
+    run: usize, // Length of the run ending at prev, capped just above the longest run any rule asks for
+    sum: u32, // Capped just above the highest digit sum any rule allows
+    seen: u64, // Bit for each run rule, by index in the policy, that an earlier run satisfied
+    low: bool, // Whether the prefix so far equals the start of the range, and so on for the end
+    high: bool,
+}
+
+// Sequences from start to end inclusive, of the same length, that follow every rule, counted without visiting each one
+struct DigitCounter<'a> {
+    policy: &'a [Rule],
+    start: &'a [u32],
+    end: &'a [u32],
+    forbidden: u16,
+    monotonic: bool,
+    max_run: usize,
+    max_sum: u32,
+    memo: HashMap<DigitState, u128>,
+}
+
+impl<'a> DigitCounter<'a> {
+    fn new(policy: &'a [Rule], start: &'a [u32], end: &'a [u32]) -> Self {
+        let mut forbidden = 0;
+        let mut max_run = 1;
+        let mut max_sum = 0;
+        for rule in policy {
+            match *rule {
+                Rule::NonDecreasing => (),
+                Rule::RunExactly(k) | Rule::RunAtLeast(k) => max_run = max_run.max(k + 1),
+                // No sum of the digits goes past 9 for each of them
+                Rule::DigitSum(_, hi) => max_sum = max_sum.max(hi.saturating_add(1).min(9 * start.len() as u32 + 1)),
+                Rule::Forbidden(mask) => forbidden |= mask,
+            }
+        }
+        let monotonic = policy.contains(&Rule::NonDecreasing);
+        DigitCounter {policy, start, end, forbidden, monotonic, max_run, max_sum, memo: HashMap::new()}
+    }
+
+    // None if the count doesn't fit in u128, which takes 39 or more digits
+    fn count(&mut self) -> Option<u128> {
+        if self.start.len() != self.end.len() {
+            return Some(0);
         }
-        increment_monotonic_sequence(&mut current);
+        let state = DigitState {position: 0, prev: None, run: 0, sum: 0, seen: 0, low: true, high: true};
+        self.count_from(state)
     }
 
-    println!("{} {}", repeating_count, doubled_count);
+    // Run rules satisfied by a run that has just ended
+    fn close_run(&self, seen: u64, run: usize) -> u64 {
+        self.policy.iter().enumerate().fold(seen, |seen, (i, rule)| match *rule {
+            Rule::RunExactly(k) if run == k => seen | 1 << i,
+            Rule::RunAtLeast(k) if run >= k => seen | 1 << i,
+            _ => seen,
+        })
+    }
+
+    fn count_from(&mut self, state: DigitState) -> Option<u128> {
+        if state.position == self.start.len() {
+            let seen = self.close_run(state.seen, state.run);
+            let allowed = self.policy.iter().enumerate().all(|(i, rule)| match *rule {
+                Rule::RunExactly(_) | Rule::RunAtLeast(_) => seen & 1 << i != 0,
+                Rule::DigitSum(lo, hi) => (lo..=hi).contains(&state.sum),
+                // Digits breaking these were never chosen
+                Rule::NonDecreasing | Rule::Forbidden(_) => true,
+            });
+            return Some(allowed as u128);
+        }
+        if let Some(&count) = self.memo.get(&state) {
+            return Some(count);
+        }
+
+        let lo = if state.low { self.start[state.position] } else { 0 };
+        let hi = if state.high { self.end[state.position] } else { 9 };
+        let mut count = 0;
+        for digit in lo..=hi {
+            if self.forbidden & 1 << digit != 0 || (self.monotonic && state.prev.is_some_and(|prev| digit < prev)) {
+                continue;
+            }
+            let (run, seen) = match state.prev {
+                Some(prev) if prev == digit => ((state.run + 1).min(self.max_run), state.seen),
+                Some(_) => (1, self.close_run(state.seen, state.run)),
+                None => (1, state.seen),
+            };
+            count = self.count_from(DigitState {
+                position: state.position + 1,
+                prev: Some(digit),
+                run,
+                sum: (state.sum + digit).min(self.max_sum),
+                seen,
+                low: state.low && digit == lo,
+                high: state.high && digit == hi,
+            })?.checked_add(count)?;
+        }
+        self.memo.insert(state, count);
+        Some(count)
+    }
+}
+
+#[test]
+fn test_digit_counter() {
+    let policies = ["nondecreasing,run>=2", "nondecreasing,run=2", "run=3,forbid=05", "sum=10..12,run>=2",
+        "nondecreasing,run=1,run>=3,sum=25", "forbid=0123456789", ""];
+    let ranges = [("123257", "647015"), ("000000", "999999"), ("5555", "5555"), ("900", "100"), ("0", "9")];
+    for policy in policies.iter() {
+        let policy = if policy.is_empty() { vec![] } else { parse_policy(policy).unwrap() };
+        for (start, end) in ranges.iter() {
+            let digits = |s: &str| s.chars().map(|c| c.to_digit(10).unwrap()).collect::<Vec<u32>>();
+            let (start, end) = (digits(start), digits(end));
+            assert_eq!(DigitCounter::new(&policy, &start, &end).count(), Some(count_matching(&policy, &start, &end) as u128),
+                "{:?} {:?}-{:?}", policy, start, end);
+        }
+    }
+
+    // Non-decreasing sequences of 18 digits correspond to multisets of 18 digits, C(27, 9) of them
+    let (start, end) = ([0; 18], [9; 18]);
+    assert_eq!(DigitCounter::new(&[Rule::NonDecreasing], &start, &end).count(), Some(4686825));
+    assert_eq!(DigitCounter::new(&[], &start, &end).count(), Some(10u128.pow(18)));
+    let policy = parse_policy("nondecreasing,run=2,sum=50..80").unwrap();
+    assert!(DigitCounter::new(&policy, &[1; 18], &[8; 18]).count().unwrap() > 0);
+
+    // Sums up to u32::MAX, and 64 rules
+    let (start, end) = ([1, 0, 0, 0, 0, 0], [9, 9, 9, 9, 9, 9]);
+    let policy = parse_policy("nondecreasing,sum=5..4294967295").unwrap();
+    assert_eq!(DigitCounter::new(&policy, &start, &end).count(), Some(3003));
+    assert_eq!(DigitCounter::new(&policy, &start, &end).count(), Some(count_matching(&policy, &start, &end) as u128));
+    let policy = parse_policy(&format!("nondecreasing,{}", vec!["run>=2"; 63].join(","))).unwrap();
+    assert_eq!(DigitCounter::new(&policy, &start, &end).count(), Some(count_matching(&policy, &start, &end) as u128));
+}
+
+// Sequences following policy across every piece of a split range, either visiting each candidate or counting
+// them digit by digit
+fn count_ranges(policy: &[Rule], ranges: &[(Vec<u32>, Vec<u32>)], enumerate: bool) -> io::Result<u128> {
+    ranges.iter()
+        .try_fold(0u128, |total, (start, end)| {
+            let count = if enumerate {
+                Some(count_matching(policy, start, end) as u128)
+            } else {
+                DigitCounter::new(policy, start, end).count()
+            };
+            total.checked_add(count?)
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Count of matching passwords overflows u128"))
+}
+
+#[test]
+fn test_count_ranges() {
+    // Every sequence of 38 digits still fits in u128, but not every sequence up to 39 digits
+    let policy = parse_policy("forbid=").unwrap();
+    assert_eq!(count_ranges(&policy, &split_range(&[0; 38], &[9; 38]), false).unwrap(), 10u128.pow(38));
+    assert_eq!(count_ranges(&policy, &split_range(&[1], &[9; 38]), false).unwrap(), 10u128.pow(38) - 1);
+    assert_eq!(DigitCounter::new(&policy, &[0; 39], &[9; 39]).count(), None);
+    let error = count_ranges(&policy, &split_range(&[1], &[9; 39]), false).unwrap_err();
+    assert_eq!(error.to_string(), "Count of matching passwords overflows u128");
+    assert_eq!(count_ranges(&policy, &split_range(&[9, 8], &[1, 0, 1]), true).unwrap(), 4);
 }
 
 fn first_monotonic_sequence(input: &[u32]) -> Vec<u32> {
@@ -50,7 +469,11 @@ fn test_first_monotonic_sequence() {
     assert_eq!(first_monotonic_sequence(&[2, 6, 4, 7, 9, 3]), &[2, 6, 6, 6, 6, 6]);
 }
 
+// Whether a comes no later than b, where shorter sequences come first
 fn is_sequence_le(a: &[u32], b: &[u32]) -> bool {
+    if a.len() != b.len() {
+        return a.len() < b.len();
+    }
     for (da, db) in a.iter().zip(b.iter()) {
         match da.cmp(&db) {
             Ordering::Less => return true,
@@ -67,9 +490,12 @@ fn test_is_sequence_le() {
     assert_eq!(is_sequence_le(&[1, 2, 3], &[1, 2, 4]), true);
     assert_eq!(is_sequence_le(&[1, 2, 3], &[2, 0, 0]), true);
     assert_eq!(is_sequence_le(&[1, 2, 3], &[1, 2, 2]), false);
+    assert_eq!(is_sequence_le(&[9, 9], &[1, 0, 0]), true);
+    assert_eq!(is_sequence_le(&[1, 0, 0], &[9, 9]), false);
 }
 
-fn increment_monotonic_sequence(sequence: &mut [u32]) {
+// Next non-decreasing sequence, or false after wrapping around to zeros
+fn increment_monotonic_sequence(sequence: &mut [u32]) -> bool {
     let mut replacement = 0;
     for &digit in sequence.iter().rev() {
         if digit < 9 {
@@ -80,17 +506,18 @@ fn increment_monotonic_sequence(sequence: &mut [u32]) {
     for digit in sequence.iter_mut().rev() {
         if *digit < 9 {
             *digit += 1;
-            return;
+            return true;
         } else {
             *digit = replacement;
         }
     }
+    false
 }
 
 #[cfg(test)]
 fn test_increment_monotonic_sequence_helper(input: &[u32], output: &[u32]) {
     let mut copy = Vec::from(input);
-    increment_monotonic_sequence(&mut copy);
+    assert_eq!(increment_monotonic_sequence(&mut copy), output.iter().any(|&d| d != 0));
     assert_eq!(copy, output);
 }
 
@@ -104,66 +531,48 @@ fn test_increment_monotonic_sequence() {
     test_increment_monotonic_sequence_helper(&[9, 9, 9], &[0, 0, 0]);
 }
 
-fn is_sequence_repeating(sequence: &[u32]) -> bool {
-    let mut prev = sequence[0];
-    let mut repeated = false;
-    for &digit in sequence.iter().skip(1) {
-        if digit == prev {
-            repeated = true;
+// Next sequence counting up by one, or false after wrapping around to zeros
+fn increment_sequence(sequence: &mut [u32]) -> bool {
+    for digit in sequence.iter_mut().rev() {
+        if *digit < 9 {
+            *digit += 1;
+            return true;
         }
-        prev = digit;
+        *digit = 0;
     }
-    repeated
+    false
 }
 
 #[test]
-fn test_is_sequence_repeating() {
-    assert_eq!(is_sequence_repeating(&[1, 1, 1, 1, 1, 1]), true);
-    assert_eq!(is_sequence_repeating(&[2, 2, 3, 4, 5, 0]), true);
-    assert_eq!(is_sequence_repeating(&[1, 2, 3, 7, 8, 9]), false);
-}
-
-fn is_sequence_doubled(sequence: &[u32]) -> bool {
-    let mut prev = sequence[0];
-    let mut repeat_count = 0;
-    let mut doubled = false;
-    for &digit in sequence.iter().skip(1) {
-        if digit == prev {
-            repeat_count += 1;
-        } else {
-            if repeat_count == 1 {
-                doubled = true;
-            }
-            repeat_count = 0;
-        }
-        prev = digit;
-    }
-    doubled || repeat_count == 1
+fn test_increment_sequence() {
+    let mut sequence = vec![1, 8, 9];
+    assert!(increment_sequence(&mut sequence));
+    assert_eq!(sequence, &[1, 9, 0]);
+    let mut sequence = vec![9, 9];
+    assert!(!increment_sequence(&mut sequence));
+    assert_eq!(sequence, &[0, 0]);
 }
 
 #[test]
-fn test_is_sequence_doubled() {
-    assert_eq!(is_sequence_doubled(&[1, 1, 2, 2, 3, 3]), true);
-    assert_eq!(is_sequence_doubled(&[1, 2, 3, 4, 4, 4]), false);
-    assert_eq!(is_sequence_doubled(&[1, 1, 1, 1, 2, 2]), true);
-}
+fn test_rule_check() {
+    assert!(Rule::RunAtLeast(2).check(&[1, 1, 1, 1, 1, 1]));
+    assert!(Rule::RunAtLeast(2).check(&[2, 2, 3, 4, 5, 0]));
+    assert!(!Rule::RunAtLeast(2).check(&[1, 2, 3, 7, 8, 9]));
 
-#[cfg(test)]
-fn is_sequence_monotonic(sequence: &[u32]) -> bool {
-    let mut max = sequence[0];
-    for &digit in sequence.iter().skip(1) {
-        if digit < max {
-            return false;
-        } else {
-            max = digit;
-        }
-    }
-    true
+    assert!(Rule::RunExactly(2).check(&[1, 1, 2, 2, 3, 3]));
+    assert!(!Rule::RunExactly(2).check(&[1, 2, 3, 4, 4, 4]));
+    assert!(Rule::RunExactly(2).check(&[1, 1, 1, 1, 2, 2]));
+    assert!(Rule::RunExactly(3).check(&[1, 2, 3, 4, 4, 4]));
+
+    assert!(Rule::DigitSum(6, 6).check(&[1, 2, 3]));
+    assert!(!Rule::DigitSum(7, 9).check(&[1, 2, 3]));
+    assert!(Rule::Forbidden(0b1).check(&[1, 2, 3]));
+    assert!(!Rule::Forbidden(0b100).check(&[1, 2, 3]));
 }
 
 #[test]
 fn test_is_sequence_monotonic() {
-    assert_eq!(is_sequence_monotonic(&[1, 1, 1, 1, 1, 1]), true);
-    assert_eq!(is_sequence_monotonic(&[2, 2, 3, 4, 5, 0]), false);
-    assert_eq!(is_sequence_monotonic(&[1, 2, 3, 7, 8, 9]), true);
+    assert!(Rule::NonDecreasing.check(&[1, 1, 1, 1, 1, 1]));
+    assert!(!Rule::NonDecreasing.check(&[2, 2, 3, 4, 5, 0]));
+    assert!(Rule::NonDecreasing.check(&[1, 2, 3, 7, 8, 9]));
 }
@@ -1,12 +1,13 @@
 #[macro_use]
 extern crate quick_error;
 
-use std::borrow::Cow;
-use std::cell::Cell;
-use std::collections::HashMap;
-use std::env;
-use std::fs::File;
-use std::io::{self, prelude::*, BufReader};
+use aoc2019::cli::Args;
+use aoc2019::orbit::{ExportOptions, OrbitIndex, OrbitTree};
+#[cfg(test)]
+use aoc2019::orbit::generate_orbits;
+
+use std::fs;
+use std::io;
 use std::num::ParseIntError;
 
 quick_error! {
@@ -20,82 +21,96 @@ quick_error! {
 type SuperResult<T> = Result<T, SuperError>;
 
 fn main() -> SuperResult<()> {
-    let reader = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        BufReader::new(File::open(name.as_ref())?)
-    };
-
-    let mut orbits = reader.lines()
-        .map(|s| parse_orbit(&s?))
-        .collect::<SuperResult<HashMap<u16, Orbit>>>()?;
+    let args = Args::from_env();
+    let input = fs::read_to_string(args.input())?;
 
-    let com = u16::from_str_radix("COM", 36).unwrap();
-    let you = u16::from_str_radix("YOU", 36).unwrap();
-    let san = u16::from_str_radix("SAN", 36).unwrap();
+    // Everything orbits COM, so any other object orbiting nothing is a typo'd parent
+    let tree = OrbitTree::parse_with_root(&input, "COM")?;
+    let index = OrbitIndex::new(&tree);
+    let find = |name: &str| tree.id(name).ok_or_else(||
+        io::Error::new(io::ErrorKind::InvalidData, format!("No object named {}", name)));
 
-    // Insert COM into map as root
-    orbits.insert(com, Orbit::root());
+    println!("{}", count_orbits(&tree));
 
-    let orbit_count: u32 = orbits.keys()
-        .map(|&k| count_orbits(&orbits, k))
-        .sum();
+    let path = transfer_path(&index, find("YOU")?, find("SAN")?)?;
+    println!("{}", path.len() - 1);
 
-    println!("{}", orbit_count);
-    println!("{}", count_transfers(&orbits, you, san).unwrap());
+    // Show each object visited on the way, e.g. --path
+    if args.flag("path").is_some() {
+        println!("{}", path.iter().map(|&id| tree.name(id)).collect::<Vec<&str>>().join(" -> "));
+    }
 
-    Ok(())
-}
+    // Answer a file of "A B" lines with the transfers between A and B and what they both orbit, e.g. --queries=pairs
+    if let Some(queries) = args.flag("queries") {
+        for line in fs::read_to_string(queries)?.lines().filter(|line| !line.trim().is_empty()) {
+            let mut names = line.split_whitespace();
+            let (a, b) = match (names.next(), names.next(), names.next()) {
+                (Some(a), Some(b), None) => (find(a)?, find(b)?),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Expected A B but got {:?}", line)).into()),
+            };
+            println!("{} {} {} {}", tree.name(a), tree.name(b), index.distance(a, b), tree.name(index.lca(a, b)));
+        }
+    }
 
-struct Orbit {
-    id: u16,
-    count: Cell<Option<u32>>,
-}
+    // Mark the whole way from YOU to SAN in exported maps, e.g. --highlight
+    let highlight = if args.flag("highlight").is_some() { index.path(find("YOU")?, find("SAN")?) } else { vec![] };
+    // Fold unbranched chains of at least this many objects in exported maps, e.g. --collapse=5
+    let collapse = match args.flag("collapse") {
+        Some("") => 3,
+        Some(n) => match n.parse()? {
+            n if n < 2 => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Chains to collapse need at least 2 objects").into()),
+            n => n,
+        },
+        None => 0,
+    };
+    let options = ExportOptions {highlight, collapse};
 
-impl Orbit {
-    fn new(id: u16) -> Self {
-        Orbit {id, count: Cell::new(None)}
+    // Print the map as an indented tree, e.g. --tree
+    if args.flag("tree").is_some() {
+        print!("{}", tree.to_text(&options));
     }
 
-    fn root() -> Self {
-        Orbit {id: 0, count: Cell::new(Some(0))}
+    // Write the map for Graphviz, e.g. --dot=orbits.dot
+    if let Some(path) = args.flag("dot") {
+        fs::write(path, tree.to_dot(&options))?;
     }
+
+    Ok(())
 }
 
-fn count_transfers(orbits: &HashMap<u16, Orbit>, from: u16, to: u16) -> Option<u32> {
-    // Visit nodes from 'from' to root
-    let mut from_path = vec![];
-    let mut tmp = orbits[&from].id;
-    while tmp != 0 {
-        from_path.push(tmp);
-        tmp = orbits[&tmp].id;
-    }
+#[cfg(test)]
+const EXAMPLE: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN";
 
-    // Visit nodes from 'to' to root, stopping at the first common node
-    tmp = orbits[&to].id;
-    let mut depth = 0;
-    while tmp != 0 {
-        if let Some(idx) = from_path.iter().position(|&x| x == tmp) {
-            return Some(depth + idx as u32);
-        }
-        tmp = orbits[&tmp].id;
-        depth += 1;
+// Objects to transfer between to get from the object that from orbits to the one that to orbits
+fn transfer_path(index: &OrbitIndex, from: usize, to: usize) -> io::Result<Vec<usize>> {
+    let tree = index.tree();
+    match (tree.parent(from), tree.parent(to)) {
+        (Some(from), Some(to)) => Ok(index.path(from, to)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Can't transfer from or to the root")),
     }
+}
 
-    None
+#[test]
+fn test_transfer_path() {
+    let tree = OrbitTree::parse(EXAMPLE).unwrap();
+    let index = OrbitIndex::new(&tree);
+    let id = |name| tree.id(name).unwrap();
+    let names = |path: Vec<usize>| path.iter().map(|&id| tree.name(id)).collect::<Vec<&str>>().join(",");
+    assert_eq!(names(transfer_path(&index, id("YOU"), id("SAN")).unwrap()), "K,J,E,D,I");
+    assert_eq!(transfer_path(&index, id("L"), id("H")).unwrap().len() - 1, 6);
+    assert!(transfer_path(&index, id("COM"), id("H")).is_err());
 }
 
-fn count_orbits(orbits: &HashMap<u16, Orbit>, id: u16) -> u32 {
-    let orbit = &orbits[&id];
-    if orbit.count.get().is_none() {
-        orbit.count.set(Some(count_orbits(&orbits, orbit.id) + 1));
-    }
-    orbit.count.get().unwrap()
+// Direct and indirect orbits of every object
+fn count_orbits(tree: &OrbitTree) -> usize {
+    tree.depths().iter().sum()
 }
 
-fn parse_orbit(input: &str) -> SuperResult<(u16, Orbit)> {
-    let result = input.trim().split(')')
-        .map(|s| u16::from_str_radix(s, 36))
-        .collect::<Result<Vec<u16>, ParseIntError>>()?;
-    Ok((result[1], Orbit::new(result[0])))
+#[test]
+fn test_count_orbits() {
+    assert_eq!(count_orbits(&OrbitTree::parse(EXAMPLE).unwrap()), 42 + 12);
+
+    // A chain this long used to overflow the stack
+    let tree = OrbitTree::parse(&generate_orbits(100_000, 10)).unwrap();
+    assert_eq!(count_orbits(&tree), 100_000 * 100_001 / 2 + 11 * 100_001 + 1);
 }
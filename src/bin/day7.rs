@@ -4,7 +4,7 @@ extern crate quick_error;
 use itertools::Itertools;
 use rayon::prelude::*;
 
-use intcode::Intcode;
+use aoc2019::intcode::Intcode;
 
 use std::borrow::Cow;
 use std::env;
@@ -1,10 +1,12 @@
 #[macro_use]
 extern crate quick_error;
 
-use rayon::prelude::*;
+use aoc2019::cli::Args;
+use aoc2019::ocr::{self, Font};
+use aoc2019::raster::{self, Bitmap, RenderOptions, BLACK_AND_WHITE};
+use aoc2019::sif::{EncodeOptions, Semantics, SpaceImage};
 
-use std::borrow::Cow;
-use std::env;
+use std::fs;
 use std::io;
 use std::num::ParseIntError;
 
@@ -19,48 +21,95 @@ quick_error! {
 type SuperResult<T> = Result<T, SuperError>;
 
 fn main() -> SuperResult<()> {
-    let input = {
-        let name: Cow<'static, str> = env::args().nth(1)
-            .map(|s| s.into()).unwrap_or_else(|| "input".into());
-        std::fs::read_to_string(name.as_ref())?
-    };
-
-    let data = input.trim().chars()
-        .map(|c| c.to_digit(10).ok_or_else(||
-            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid digit {}", c))))
-        .collect::<io::Result<Vec<u32>>>()?;
-
-    let rows = 6;
-    let cols = 25;
-    let pixels = rows * cols;
-    let layers = data.len() / pixels;
-
-    let (_, result) = data.par_chunks(pixels)
-        .map(|layer| {
-            let mut count = [0; 3];
-            layer.iter().for_each(|&pixel| count[pixel as usize] += 1);
-            (count[0], count[1] * count[2])
-        })
-        .min_by(|(x, _), (y, _)| x.cmp(&y)).unwrap();
+    let args = Args::from_env();
+    // Choose what the digits mean, e.g. --transparent=2 --colors=01 --background=0
+    let semantics = Semantics::parse(args.flag("transparent"), args.flag("colors"), args.flag("background"))?;
 
+    // Print a line of digits compositing to each target instead, where the targets are any text in the small font
+    // and the positional arguments as PPM or PGM files, e.g. --encode=4 --noise=2 --seed=3 --text=CFLUZ hull.ppm
+    if let Some(layers) = args.flag("encode") {
+        let mut options = EncodeOptions {noise: args.flag("noise").map(str::parse).transpose()?.unwrap_or(0), ..Default::default()};
+        if !layers.is_empty() {
+            options.layers = layers.parse()?;
+        }
+        if let Some(seed) = args.flag("seed") {
+            options.seed = seed.parse()?;
+        }
+        // Colors of the pixel values in the files, e.g. --palette=000000,ffffff
+        let palette = args.flag("palette").map(raster::parse_palette).transpose()?.unwrap_or_else(|| BLACK_AND_WHITE.to_vec());
+        let mut targets = args.flag("text").map(|text| ocr::render(text, Font::Small)).into_iter().collect::<io::Result<Vec<Bitmap>>>()?;
+        for path in args.positional.iter() {
+            targets.push(raster::load(path, &palette)?);
+        }
+        for (i, target) in targets.iter().enumerate() {
+            let options = EncodeOptions {seed: options.seed.wrapping_add(i as u64), ..options.clone()};
+            println!("{}", SpaceImage::encode(target, &semantics, &options)?.to_digits());
+        }
+        return Ok(());
+    }
+
+    let input = fs::read_to_string(args.input())?;
+
+    // Layer size in pixels, e.g. --width=25 --height=6
+    let width = args.flag("width").map(str::parse).transpose()?.unwrap_or(25);
+    let height = args.flag("height").map(str::parse).transpose()?.unwrap_or(6);
+    let image = SpaceImage::parse(&input, width, height)?;
+    let histograms = image.histograms();
+
+    // Show how often each digit appears in every layer, e.g. --histogram
+    if args.flag("histogram").is_some() {
+        print!("{}", format_histograms(&histograms));
+    }
+
+    let result = checksum(&histograms);
     println!("Part 1: {}", result);
 
-    let image = (0..rows).into_par_iter().map(|row| {
-        (0..cols).into_par_iter().map(|col| {
-            let i = row * cols + col;
-            for j in 0..layers {
-                match data[j * pixels + i] {
-                    0 => return Ok('🀫'),
-                    1 => return Ok('🀆'),
-                    _ => continue,
-                }
-            }
-            Err(io::Error::new(io::ErrorKind::InvalidData,
-                format!("Invalid pixel ({}, {})", col, row)))
-        }).collect::<io::Result<String>>()
-    }).collect::<io::Result<Vec<String>>>()?.join("\n");
-
-    println!("Part 2:\n{}", image);
+    let decoded = image.decode(&semantics)?;
+
+    println!("Part 2:\n{}", decoded.to_text(&['🀫', '🀆']));
+    if let Ok(text) = ocr::recognize(&decoded, semantics.colors[1]) {
+        println!("{}", text);
+    }
+
+    // Optionally export the image, e.g. day8 input image.svg 10 000000,ffffff
+    if let Some(path) = args.get(1) {
+        let options = RenderOptions::parse(args.get(2), args.get(3))?;
+        raster::save(path, &decoded, &options)?;
+    }
 
     Ok(())
 }
+
+// Number of 1 digits times number of 2 digits on the layer with the fewest 0 digits
+fn checksum(histograms: &[[usize; 10]]) -> usize {
+    histograms.iter()
+        .min_by_key(|counts| counts[0])
+        .map(|counts| counts[1] * counts[2])
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_checksum() {
+    let image = SpaceImage::parse("123456789012", 3, 2).unwrap();
+    assert_eq!(checksum(&image.histograms()), 1);
+    let image = SpaceImage::parse("001122111220", 3, 2).unwrap();
+    assert_eq!(checksum(&image.histograms()), 3 * 2);
+}
+
+fn format_histograms(histograms: &[[usize; 10]]) -> String {
+    let mut text = format!("Layer{}\n", (0..10).map(|d| format!("{:>5}", d)).collect::<String>());
+    for (layer, counts) in histograms.iter().enumerate() {
+        text += &format!("{:>5}{}\n", layer, counts.iter().map(|c| format!("{:>5}", c)).collect::<String>());
+    }
+    text
+}
+
+#[test]
+fn test_format_histograms() {
+    let image = SpaceImage::parse("123456789012", 3, 2).unwrap();
+    assert_eq!(format_histograms(&image.histograms()), "\
+Layer    0    1    2    3    4    5    6    7    8    9
+    0    0    1    1    1    1    1    1    0    0    0
+    1    1    1    1    0    0    0    0    1    1    1
+");
+}
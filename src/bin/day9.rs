@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate quick_error;
 
-use intcode::Intcode;
+use aoc2019::intcode::Intcode;
 
 use std::borrow::Cow;
 use std::env;
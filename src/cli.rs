@@ -0,0 +1,55 @@
+use std::env;
+
+// Command line split into --name[=value] options and positional arguments
+#[derive(Clone, Default, Debug)]
+pub struct Args {
+    pub positional: Vec<String>,
+    pub flags: Vec<(String, String)>,
+}
+
+impl Args {
+    pub fn from_env() -> Self {
+        Args::parse(env::args().skip(1))
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut result = Args::default();
+        for arg in args {
+            match arg.strip_prefix("--") {
+                Some(flag) => {
+                    let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+                    result.flags.push((name.into(), value.into()));
+                }
+                None => result.positional.push(arg),
+            }
+        }
+        result
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(|s| s.as_str())
+    }
+
+    // The input file is the first positional argument, defaulting to "input"
+    pub fn input(&self) -> &str {
+        self.get(0).unwrap_or("input")
+    }
+
+    // Value of the last --name or --name=value, which is empty if no value was given
+    pub fn flag(&self, name: &str) -> Option<&str> {
+        self.flags.iter().rev().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+#[test]
+fn test_args() {
+    let args = Args::parse(vec!["map.txt", "--animate", "out.ppm", "--scale=4", "--scale=5"]
+        .into_iter().map(String::from));
+    assert_eq!(args.input(), "map.txt");
+    assert_eq!(args.get(1), Some("out.ppm"));
+    assert_eq!(args.get(2), None);
+    assert_eq!(args.flag("animate"), Some(""));
+    assert_eq!(args.flag("scale"), Some("5"));
+    assert_eq!(args.flag("frames"), None);
+    assert_eq!(Args::parse(vec![]).input(), "input");
+}
@@ -0,0 +1,238 @@
+#[cfg(test)]
+use crate::rng::Xorshift;
+
+use rayon::prelude::*;
+
+use std::fmt::Display;
+use std::io;
+use std::iter::Sum;
+use std::ops::{Add, Div, Sub};
+use std::str::FromStr;
+
+// Unsigned integers wide enough for the masses at hand, such as u64 or u128
+pub trait Mass: Copy + Ord + Display + FromStr + Sum + From<u8> + Add<Output = Self> + Sub<Output = Self> + Div<Output = Self> {}
+
+impl<T> Mass for T
+    where T: Copy + Ord + Display + FromStr + Sum + From<u8> + Add<Output = T> + Sub<Output = T> + Div<Output = T> {}
+
+// Fuel to launch a mass, ignoring the mass of the fuel itself
+pub fn compute_fuel<T: Mass>(mass: T) -> T {
+    if mass > T::from(2 * 3) {
+        mass / T::from(3) - T::from(2)
+    } else {
+        T::from(0)
+    }
+}
+
+#[test]
+fn test_compute_fuel() {
+    assert_eq!(compute_fuel(0u64), 0);
+    assert_eq!(compute_fuel(12u64), 2);
+    assert_eq!(compute_fuel(14u64), 2);
+    assert_eq!(compute_fuel(1969u64), 654);
+    assert_eq!(compute_fuel(100756u64), 33583);
+    assert_eq!(compute_fuel(u128::MAX), u128::MAX / 3 - 2);
+}
+
+// Fuel to launch a mass, then fuel for that fuel and so on. While fuel is positive, compute_fuel(m) + 3 is
+// (m + 3) / 3, so each round is one division of the previous round plus 3, and the first round avoids overflowing m + 3.
+// This is still one round per power of 3 in the mass, like compute_fuel_fuel_iterative; large lists of modules
+// are only faster because batch_totals spreads them over threads
+pub fn compute_fuel_fuel<T: Mass>(mass: T) -> T {
+    let three = T::from(3);
+    let mut total = T::from(0);
+    let mut shifted = compute_fuel(mass) + three;
+    while shifted > three {
+        total = total + (shifted - three);
+        shifted = shifted / three;
+    }
+    total
+}
+
+// The definition compute_fuel_fuel has to agree with
+#[cfg(test)]
+fn compute_fuel_fuel_iterative<T: Mass>(mass: T) -> T {
+    let zero = T::from(0);
+    let mut total = zero;
+    let mut fuel = compute_fuel(mass);
+    while fuel > zero {
+        total = total + fuel;
+        fuel = compute_fuel(fuel);
+    }
+    total
+}
+
+#[test]
+fn test_compute_fuel_fuel() {
+    assert_eq!(compute_fuel_fuel(14u64), 2);
+    assert_eq!(compute_fuel_fuel(1969u64), 966);
+    assert_eq!(compute_fuel_fuel(100756u64), 50346);
+    assert_eq!(compute_fuel_fuel(100756u128), 50346);
+    assert_eq!(compute_fuel_fuel(u128::MAX), compute_fuel_fuel_iterative(u128::MAX));
+}
+
+#[test]
+fn test_compute_fuel_fuel_property() {
+    let mut rng = Xorshift::new(5);
+    let mut next = move || rng.next_u64();
+    for mass in 0..10_000u64 {
+        assert_eq!(compute_fuel_fuel(mass), compute_fuel_fuel_iterative(mass), "{}", mass);
+    }
+    for _ in 0..10_000 {
+        // Shift away a random number of bits so that small and huge masses are both common
+        let mass = next() >> (next() % 64);
+        assert_eq!(compute_fuel_fuel(mass), compute_fuel_fuel_iterative(mass), "{}", mass);
+        let mass = ((next() as u128) << 64 | next() as u128) >> (next() % 128);
+        assert_eq!(compute_fuel_fuel(mass), compute_fuel_fuel_iterative(mass), "{}", mass);
+    }
+}
+
+// Modules each thread handles at a time in batch_totals
+const BATCH: usize = 1 << 14;
+
+// Fuel and fuel including fuel for fuel summed over every module across threads, or None if a sum overflows u128
+pub fn batch_totals<T: Mass + Into<u128> + Send + Sync>(masses: &[T]) -> Option<(u128, u128)> {
+    let add = |a: Option<(u128, u128)>, b: Option<(u128, u128)>| match (a, b) {
+        (Some(a), Some(b)) => Some((a.0.checked_add(b.0)?, a.1.checked_add(b.1)?)),
+        _ => None,
+    };
+    masses.par_chunks(BATCH)
+        .map(|batch| batch.iter()
+            .map(|&mass| Some((compute_fuel(mass).into(), compute_fuel_fuel(mass).into())))
+            .fold(Some((0, 0)), add))
+        .reduce(|| Some((0, 0)), add)
+}
+
+#[test]
+fn test_batch_totals() {
+    let masses = (0..200_000u64).map(|i| i * i * 7919).collect::<Vec<u64>>();
+    let expected = masses.iter().fold((0u128, 0u128), |(fuel, total), &mass|
+        (fuel + compute_fuel(mass) as u128, total + compute_fuel_fuel_iterative(mass) as u128));
+    assert_eq!(batch_totals(&masses), Some(expected));
+    assert_eq!(batch_totals::<u64>(&[]), Some((0, 0)));
+
+    // Sums that don't fit in u64 still fit in u128
+    let masses = vec![u64::MAX; 100];
+    assert_eq!(batch_totals(&masses).unwrap().0, (u64::MAX / 3 - 2) as u128 * 100);
+    assert_eq!(batch_totals(&[u128::MAX; 4]), None);
+}
+
+// One mass per line, ignoring blank lines; every line that isn't a mass is reported
+pub fn parse_masses<T: Mass>(input: &str) -> io::Result<Vec<T>> {
+    let mut masses = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse() {
+            Ok(mass) => masses.push(mass),
+            Err(_) => errors.push(format!("{} ({:?})", i + 1, line)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid mass at line {}", errors.join(", "))));
+    }
+    Ok(masses)
+}
+
+#[test]
+fn test_parse_masses() {
+    assert_eq!(parse_masses::<u64>("12\n 14 \n\n1969\n").unwrap(), &[12, 14, 1969]);
+    let error = parse_masses::<u64>("12\nabc\n-3\n14").unwrap_err();
+    assert_eq!(error.to_string(), "Invalid mass at line 2 (\"abc\"), 3 (\"-3\")");
+    assert!(parse_masses::<u64>("18446744073709551616").is_err());
+    assert_eq!(parse_masses::<u128>("18446744073709551616").unwrap(), &[1 << 64]);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ModuleFuel<T> {
+    pub mass: T,
+    pub fuel: T,
+    pub fuel_fuel: T, // Fuel for the fuel, on top of fuel
+}
+
+impl<T: Mass> ModuleFuel<T> {
+    pub fn new(mass: T) -> Self {
+        let fuel = compute_fuel(mass);
+        ModuleFuel {mass, fuel, fuel_fuel: compute_fuel_fuel(mass) - fuel}
+    }
+
+    pub fn total(&self) -> T {
+        self.fuel + self.fuel_fuel
+    }
+}
+
+impl<T: Mass + Into<u128>> ModuleFuel<T> {
+    fn widen(&self) -> ModuleFuel<u128> {
+        ModuleFuel {mass: self.mass.into(), fuel: self.fuel.into(), fuel_fuel: self.fuel_fuel.into()}
+    }
+}
+
+// Sums of each column over every module, or None if one overflows u128, which enough u128 masses can
+pub fn totals<T: Mass + Into<u128>>(modules: &[ModuleFuel<T>]) -> Option<ModuleFuel<u128>> {
+    modules.iter().try_fold(ModuleFuel {mass: 0, fuel: 0, fuel_fuel: 0}, |sums: ModuleFuel<u128>, m| {
+        let m = m.widen();
+        Some(ModuleFuel {
+            mass: sums.mass.checked_add(m.mass)?,
+            fuel: sums.fuel.checked_add(m.fuel)?,
+            fuel_fuel: sums.fuel_fuel.checked_add(m.fuel_fuel)?,
+        })
+    })
+}
+
+#[test]
+fn test_totals() {
+    let modules = [u64::MAX, u64::MAX].iter().map(|&m| ModuleFuel::new(m)).collect::<Vec<ModuleFuel<u64>>>();
+    let sums = totals(&modules).unwrap();
+    assert_eq!(sums.mass, u64::MAX as u128 * 2);
+    assert_eq!(sums.total(), compute_fuel_fuel(u64::MAX) as u128 * 2);
+    assert_eq!(totals::<u64>(&[]), Some(ModuleFuel {mass: 0, fuel: 0, fuel_fuel: 0}));
+    assert_eq!(totals(&[ModuleFuel::new(u128::MAX), ModuleFuel::new(1)]), None);
+}
+
+// Right aligned columns for each module, then their totals
+pub fn format_table<T: Mass + Into<u128>>(modules: &[ModuleFuel<T>]) -> io::Result<String> {
+    let sums = totals(modules).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Total fuel overflows u128"))?;
+    let header = ["Module", "Mass", "Fuel", "Fuel for fuel", "Total"].iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let row = |name: String, m: &ModuleFuel<u128>| vec![name, m.mass.to_string(), m.fuel.to_string(), m.fuel_fuel.to_string(), m.total().to_string()];
+    let mut rows = vec![header];
+    rows.extend(modules.iter().enumerate().map(|(i, m)| row((i + 1).to_string(), &m.widen())));
+    rows.push(row("Total".to_string(), &sums));
+
+    let widths = (0..5).map(|c| rows.iter().map(|r| r[c].len()).max().unwrap()).collect::<Vec<usize>>();
+    Ok(rows.iter()
+        .map(|r| r.iter().zip(widths.iter()).map(|(cell, &width)| format!("{:>1$}", cell, width)).collect::<Vec<String>>().join("  ") + "\n")
+        .collect())
+}
+
+#[test]
+fn test_format_table() {
+    let modules = [12u64, 1969].iter().map(|&m| ModuleFuel::new(m)).collect::<Vec<ModuleFuel<u64>>>();
+    assert_eq!(modules[1], ModuleFuel {mass: 1969, fuel: 654, fuel_fuel: 312});
+    assert_eq!(format_table(&modules).unwrap(), "\
+Module  Mass  Fuel  Fuel for fuel  Total
+     1    12     2              0      2
+     2  1969   654            312    966
+ Total  1981   656            312    968
+");
+
+    let modules = [u64::MAX; 2].iter().map(|&m| ModuleFuel::new(m)).collect::<Vec<ModuleFuel<u64>>>();
+    assert!(format_table(&modules).unwrap().lines().last().unwrap().starts_with(" Total  36893488147419103230  "));
+    assert!(format_table(&[ModuleFuel::new(u128::MAX); 2]).is_err());
+}
+
+pub fn format_csv<T: Mass>(modules: &[ModuleFuel<T>]) -> String {
+    let mut csv = "module,mass,fuel,fuel_for_fuel,total\n".to_string();
+    for (i, m) in modules.iter().enumerate() {
+        csv += &format!("{},{},{},{},{}\n", i + 1, m.mass, m.fuel, m.fuel_fuel, m.total());
+    }
+    csv
+}
+
+#[test]
+fn test_format_csv() {
+    let modules = [14u64, 100756].iter().map(|&m| ModuleFuel::new(m)).collect::<Vec<ModuleFuel<u64>>>();
+    assert_eq!(format_csv(&modules), "module,mass,fuel,fuel_for_fuel,total\n1,14,2,0,2\n2,100756,33583,16763,50346\n");
+}
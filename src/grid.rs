@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+
+// Screen coordinates: x grows to the right and y grows downwards
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point {x: 0, y: 0};
+
+    pub const fn new(x: isize, y: isize) -> Self {
+        Point {x, y}
+    }
+
+    // Manhattan distance from the origin
+    pub fn manhattan(self) -> usize {
+        self.x.unsigned_abs() + self.y.unsigned_abs()
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = Point> {
+        Direction::ALL.iter().map(move |d| d.step(self))
+    }
+
+    // Includes diagonals, clockwise from straight up
+    pub fn neighbors8(self) -> impl Iterator<Item = Point> {
+        const OFFSETS: [Point; 8] = [Point::new(0, -1), Point::new(1, -1), Point::new(1, 0), Point::new(1, 1),
+            Point::new(0, 1), Point::new(-1, 1), Point::new(-1, 0), Point::new(-1, -1)];
+        OFFSETS.iter().map(move |&offset| self + offset)
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for Point {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<isize> for Point {
+    type Output = Self;
+    fn mul(self, rhs: isize) -> Self {
+        Point::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Neg for Point {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+#[test]
+fn test_point() {
+    assert_eq!(Point::new(1, 2) + Point::new(3, -4), Point::new(4, -2));
+    assert_eq!(Point::new(4, 6) - Point::new(3, 4), Point::new(1, 2));
+    assert_eq!(Point::new(1, -2) * 3, Point::new(3, -6));
+    assert_eq!(-Point::new(1, -2), Point::new(-1, 2));
+    assert_eq!(Point::new(2, 3).manhattan(), 5);
+    assert_eq!(Point::new(-1, -2).manhattan(), 3);
+    assert_eq!(Point::ORIGIN.neighbors().collect::<Vec<Point>>(),
+        &[Point::new(0, -1), Point::new(1, 0), Point::new(0, 1), Point::new(-1, 0)]);
+    assert_eq!(Point::new(5, 5).neighbors8().count(), 8);
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    // Clockwise from straight up
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    pub fn turn(self, turn: Turn) -> Self {
+        match turn {
+            Turn::Left => self.turn_left(),
+            Turn::Right => self.turn_right(),
+        }
+    }
+
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        self.turn_left().turn_left()
+    }
+
+    // Screen coordinates, so up is towards negative y
+    pub fn offset(self) -> Point {
+        match self {
+            Direction::Up => Point::new(0, -1),
+            Direction::Right => Point::new(1, 0),
+            Direction::Down => Point::new(0, 1),
+            Direction::Left => Point::new(-1, 0),
+        }
+    }
+
+    pub fn step(self, position: Point) -> Point {
+        position + self.offset()
+    }
+}
+
+#[test]
+fn test_direction() {
+    assert_eq!(Direction::Up.turn_left(), Direction::Left);
+    assert_eq!(Direction::Left.turn_left(), Direction::Down);
+    assert_eq!(Direction::Up.turn_right(), Direction::Right);
+    assert_eq!(Direction::Left.turn(Turn::Right), Direction::Up);
+    assert_eq!(Direction::Right.reverse(), Direction::Left);
+    assert_eq!(Direction::Up.step(Point::ORIGIN), Point::new(0, -1));
+    assert_eq!(Direction::Right.step(Point::ORIGIN), Point::new(1, 0));
+    assert_eq!(Direction::Down.step(Point::ORIGIN), Point::new(0, 1));
+    assert_eq!(Direction::Left.step(Point::ORIGIN), Point::new(-1, 0));
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+// Inclusive rectangle of points
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn new(point: Point) -> Self {
+        Bounds {min: point, max: point}
+    }
+
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let mut bounds = Bounds::new(points.next()?);
+        points.for_each(|p| bounds.include(p));
+        Some(bounds)
+    }
+
+    pub fn include(&mut self, point: Point) {
+        self.min = Point::new(self.min.x.min(point.x), self.min.y.min(point.y));
+        self.max = Point::new(self.max.x.max(point.x), self.max.y.max(point.y));
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    // Row-major, top left to bottom right
+    pub fn points(self) -> impl Iterator<Item = Point> {
+        (self.min.y..=self.max.y).flat_map(move |y| (self.min.x..=self.max.x).map(move |x| Point::new(x, y)))
+    }
+}
+
+#[test]
+fn test_bounds() {
+    assert_eq!(Bounds::from_points(vec![]), None);
+    let bounds = Bounds::from_points(vec![Point::new(2, -1), Point::new(-1, 3), Point::new(0, 0)]).unwrap();
+    assert_eq!(bounds, Bounds {min: Point::new(-1, -1), max: Point::new(2, 3)});
+    assert_eq!((bounds.width(), bounds.height()), (4, 5));
+    assert!(bounds.contains(Point::new(2, 3)));
+    assert!(!bounds.contains(Point::new(3, 3)));
+    assert_eq!(bounds.points().count(), 20);
+    assert_eq!(bounds.points().nth(5), Some(Point::new(0, 0)));
+}
+
+fn extent(width: usize, height: usize) -> Option<Bounds> {
+    if width == 0 || height == 0 {
+        None
+    } else {
+        Some(Bounds {min: Point::ORIGIN, max: Point::new(width as isize - 1, height as isize - 1)})
+    }
+}
+
+// Dense, row-major grid with the origin at the top left
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Grid {width, height, cells: vec![fill; width * height]}
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_cells(width: usize, height: usize, cells: Vec<T>) -> io::Result<Self> {
+        if cells.len() != width * height {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("Expected {} cells for {}x{} grid, got {}", width * height, width, height, cells.len())));
+        }
+        Ok(Grid {width, height, cells})
+    }
+
+    pub fn from_fn<F: FnMut(Point) -> T>(width: usize, height: usize, f: F) -> Self {
+        let cells = extent(width, height).map(|b| b.points().map(f).collect()).unwrap_or_default();
+        Grid {width, height, cells}
+    }
+
+    // Parse one cell per character, ignoring surrounding whitespace on each line
+    pub fn parse<F: FnMut(char) -> Option<T>>(input: &str, f: F) -> io::Result<Self> {
+        Grid::parse_lines(input.trim().lines().map(str::trim), f)
+    }
+
+    // Like parse, but whitespace is a cell like any other, so errors point at the real line and column
+    pub fn parse_exact<F: FnMut(char) -> Option<T>>(input: &str, f: F) -> io::Result<Self> {
+        Grid::parse_lines(input.lines(), f)
+    }
+
+    fn parse_lines<'a, I, F>(lines: I, mut f: F) -> io::Result<Self>
+        where I: Iterator<Item = &'a str>, F: FnMut(char) -> Option<T>
+    {
+        let mut width = None;
+        let mut cells = Vec::new();
+        let mut height = 0;
+        for (row, line) in lines.enumerate() {
+            let start = cells.len();
+            for (col, c) in line.chars().enumerate() {
+                cells.push(f(c).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                    format!("Invalid cell {:?} at line {} column {}", c, row + 1, col + 1)))?);
+            }
+            let len = cells.len() - start;
+            if *width.get_or_insert(len) != len {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("Line {} has {} cells, expected {}", row + 1, len, width.unwrap())));
+            }
+            height += 1;
+        }
+        Ok(Grid {width: width.unwrap_or(0), height, cells})
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        extent(self.width, self.height)
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= 0 && point.y >= 0 && (point.x as usize) < self.width && (point.y as usize) < self.height
+    }
+
+    fn index_of(&self, point: Point) -> Option<usize> {
+        if self.contains(point) {
+            Some(point.y as usize * self.width + point.x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn point_of(&self, index: usize) -> Point {
+        Point::new((index % self.width) as isize, (index / self.width) as isize)
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.index_of(point).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.index_of(point).map(move |i| &mut self.cells[i])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.cells.iter().enumerate().map(move |(i, cell)| (self.point_of(i), cell))
+    }
+
+    // The up to four orthogonal neighbors that lie inside the grid
+    pub fn neighbors(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        point.neighbors().filter(move |&p| self.contains(p))
+    }
+
+    pub fn neighbors8(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        point.neighbors8().filter(move |&p| self.contains(p))
+    }
+
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {width: self.width, height: self.height, cells: self.cells.iter().map(f).collect()}
+    }
+
+    // Render one character per cell
+    pub fn render<F: FnMut(&T) -> char>(&self, mut f: F) -> String {
+        self.rows()
+            .map(|row| row.iter().map(&mut f).collect::<String>())
+            .collect::<Vec<String>>().join("\n")
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+    fn index(&self, point: Point) -> &T {
+        let i = self.index_of(point).unwrap_or_else(|| panic!("{:?} is outside the grid", point));
+        &self.cells[i]
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, point: Point) -> &mut T {
+        let i = self.index_of(point).unwrap_or_else(|| panic!("{:?} is outside the grid", point));
+        &mut self.cells[i]
+    }
+}
+
+#[test]
+fn test_grid() {
+    let mut grid = Grid::parse(" .#.\n ..#", |c| match c {
+        '.' => Some(false),
+        '#' => Some(true),
+        _ => None,
+    }).unwrap();
+    assert_eq!((grid.width, grid.height), (3, 2));
+    assert!(grid[Point::new(1, 0)]);
+    assert_eq!(grid.get(Point::new(3, 0)), None);
+    assert_eq!(grid.get(Point::new(0, -1)), None);
+    grid[Point::new(0, 1)] = true;
+    assert_eq!(grid.render(|&c| if c { '#' } else { '.' }), ".#.\n#.#");
+    assert_eq!(grid.iter().filter(|(_, &c)| c).map(|(p, _)| p).collect::<Vec<Point>>(),
+        &[Point::new(1, 0), Point::new(0, 1), Point::new(2, 1)]);
+    assert_eq!(grid.neighbors(Point::new(0, 0)).collect::<Vec<Point>>(), &[Point::new(1, 0), Point::new(0, 1)]);
+    assert_eq!(grid.neighbors8(Point::new(1, 1)).count(), 5);
+    assert_eq!(grid.bounds().map(|b| (b.width(), b.height())), Some((3, 2)));
+
+    assert!(Grid::parse(".#.\n.#", Some).is_err());
+    assert!(Grid::parse(".x.", |c| if c == '.' { Some(()) } else { None }).is_err());
+    let dot = |c| if c == '.' { Some(()) } else { None };
+    assert_eq!(Grid::parse_exact("..\n..\n", dot).map(|g| g.height).unwrap(), 2);
+    assert_eq!(Grid::parse_exact("..\n .", dot).unwrap_err().to_string(), "Invalid cell ' ' at line 2 column 1");
+    assert!(Grid::parse_exact("\n..", dot).is_err());
+    assert!(Grid::from_cells(2, 2, vec![0; 3]).is_err());
+    assert_eq!(Grid::from_fn(2, 2, |p| p.x + p.y * 2).cells, &[0, 1, 2, 3]);
+    assert_eq!(Grid::new(0, 3, 0).bounds(), None);
+}
+
+// Unbounded grid that only stores the cells that have been set, remembering the extent of everything set
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point, T>,
+    bounds: Option<Bounds>,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> Self {
+        SparseGrid {cells: HashMap::new(), bounds: None}
+    }
+
+    pub fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        match self.bounds.as_mut() {
+            Some(bounds) => bounds.include(point),
+            None => self.bounds = Some(Bounds::new(point)),
+        }
+        self.cells.insert(point, value)
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        self.cells.get(&point)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.cells.iter().map(|(&p, v)| (p, v))
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    // Extent of only the cells matching a predicate, e.g. the painted ones
+    pub fn bounds_where<F: FnMut(&T) -> bool>(&self, mut f: F) -> Option<Bounds> {
+        Bounds::from_points(self.iter().filter(|(_, v)| f(v)).map(|(p, _)| p))
+    }
+
+    // Copy a window of the grid into a dense grid, filling in the cells that were never set
+    pub fn to_grid(&self, bounds: Bounds, fill: T) -> Grid<T> where T: Clone {
+        Grid::from_fn(bounds.width(), bounds.height(), |p| self.get(p + bounds.min).cloned().unwrap_or_else(|| fill.clone()))
+    }
+}
+
+impl<T> Extend<(Point, T)> for SparseGrid<T> {
+    fn extend<I: IntoIterator<Item = (Point, T)>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|(p, v)| { self.insert(p, v); });
+    }
+}
+
+impl<T> Index<Point> for SparseGrid<T> {
+    type Output = T;
+    fn index(&self, point: Point) -> &T {
+        &self.cells[&point]
+    }
+}
+
+#[test]
+fn test_sparse_grid() {
+    let mut grid = SparseGrid::new();
+    assert_eq!(grid.bounds(), None);
+    grid.insert(Point::new(-2, 1), 1);
+    grid.insert(Point::new(1, -1), 0);
+    grid.extend(vec![(Point::new(0, 0), 1)]);
+    assert_eq!(grid.len(), 3);
+    assert_eq!(grid[Point::new(-2, 1)], 1);
+    assert_eq!(grid.get(Point::new(5, 5)), None);
+    assert_eq!(grid.bounds(), Some(Bounds {min: Point::new(-2, -1), max: Point::new(1, 1)}));
+    let painted = grid.bounds_where(|&c| c == 1).unwrap();
+    assert_eq!(painted, Bounds {min: Point::new(-2, 0), max: Point::new(0, 1)});
+    assert_eq!(grid.to_grid(painted, 0).render(|&c| if c == 1 { '#' } else { '.' }), "..#\n#..");
+}
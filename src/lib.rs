@@ -0,0 +1,10 @@
+pub mod cli;
+pub mod fuel;
+pub mod grid;
+pub mod intcode;
+pub mod ocr;
+pub mod orbit;
+pub mod raster;
+pub mod rng;
+pub mod robot;
+pub mod sif;
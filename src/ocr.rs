@@ -0,0 +1,229 @@
+use crate::grid::Point;
+use crate::raster::Bitmap;
+
+use std::io;
+
+type Glyph = (char, &'static [&'static str]);
+
+// Letters 4 pixels wide (Y is 5) and 6 tall, with one blank column between letters
+const SMALL_FONT: &[Glyph] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// Letters 6 pixels wide and 10 tall, with two blank columns between letters
+const LARGE_FONT: &[Glyph] = &[
+    ('A', &["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', &["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', &[".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('E', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', &["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', &[".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', &["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('J', &["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', &["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', &["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('N', &["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('P', &["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('R', &["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('X', &["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Z', &["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Font {
+    Small,
+    Large,
+}
+
+impl Font {
+    fn glyphs(self) -> &'static [Glyph] {
+        match self {
+            Font::Small => SMALL_FONT,
+            Font::Large => LARGE_FONT,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            Font::Small => 6,
+            Font::Large => 10,
+        }
+    }
+
+    // Columns from the start of one letter to the next, so wide letters like Y touch the letter after them
+    pub fn pitch(self) -> usize {
+        match self {
+            Font::Small => 5,
+            Font::Large => 8,
+        }
+    }
+
+    fn from_height(height: usize) -> Option<Self> {
+        [Font::Small, Font::Large].iter().cloned().find(|f| f.height() == height)
+    }
+}
+
+// Columns of a glyph as bit masks with the top row in the lowest bit, without blank columns at either side
+fn glyph_columns(rows: &[&str]) -> Vec<u32> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let columns = (0..width)
+        .map(|x| rows.iter().enumerate()
+            .filter(|(_, row)| row.as_bytes().get(x) == Some(&b'#'))
+            .fold(0, |mask, (y, _)| mask | 1 << y))
+        .collect::<Vec<u32>>();
+    trim_columns(&columns).to_vec()
+}
+
+fn trim_columns(columns: &[u32]) -> &[u32] {
+    let start = columns.iter().position(|&c| c != 0).unwrap_or(columns.len());
+    let end = columns.iter().rposition(|&c| c != 0).map(|e| e + 1).unwrap_or(start);
+    &columns[start..end]
+}
+
+// Read the letters drawn with pixels equal to ink, laid out at the font's pitch or else split on blank columns
+pub fn recognize(bitmap: &Bitmap, ink: u8) -> io::Result<String> {
+    let rows = bitmap.rows().collect::<Vec<&[u8]>>();
+    let inked = |row: &&[u8]| row.contains(&ink);
+    let top = match rows.iter().position(inked) {
+        Some(top) => top,
+        None => return Ok(String::new()),
+    };
+    let bottom = rows.iter().rposition(inked).unwrap();
+
+    let height = bottom - top + 1;
+    let font = Font::from_height(height).ok_or_else(||
+        io::Error::new(io::ErrorKind::InvalidData, format!("No font for letters {} pixels tall", height)))?;
+
+    let columns = (0..bitmap.width)
+        .map(|x| rows[top..=bottom].iter().enumerate()
+            .filter(|(_, row)| row[x] == ink)
+            .fold(0, |mask, (y, _)| mask | 1 << y))
+        .collect::<Vec<u32>>();
+
+    (0..font.pitch())
+        .find_map(|offset| read_cells(font, &columns, offset))
+        .map_or_else(|| read_runs(font, &columns), Ok)
+}
+
+fn find_glyph(font: Font, letter: &[u32]) -> Option<char> {
+    font.glyphs().iter().find(|(_, rows)| glyph_columns(rows) == letter).map(|glyph| glyph.0)
+}
+
+// Read one letter per cell of the font's pitch starting at offset, if every cell holds one or is blank
+fn read_cells(font: Font, columns: &[u32], offset: usize) -> Option<String> {
+    if columns[..offset.min(columns.len())].iter().any(|&c| c != 0) {
+        return None;
+    }
+    columns.get(offset..).unwrap_or(&[]).chunks(font.pitch())
+        .map(trim_columns)
+        .filter(|letter| !letter.is_empty())
+        .map(|letter| find_glyph(font, letter))
+        .collect()
+}
+
+// Read letters separated by blank columns, for text that isn't laid out at the font's pitch
+fn read_runs(font: Font, columns: &[u32]) -> io::Result<String> {
+    let mut text = String::new();
+    let mut x = 0;
+    while x < columns.len() {
+        if columns[x] == 0 {
+            x += 1;
+            continue;
+        }
+        let width = columns[x..].iter().take_while(|&&c| c != 0).count();
+        let letter = find_glyph(font, &columns[x..x + width]).ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidData, format!("Unrecognized letter at column {}", x)))?;
+        text.push(letter);
+        x += width;
+    }
+    Ok(text)
+}
+
+// Draw text in the given font with ink pixels of 1 on a background of 0
+pub fn render(text: &str, font: Font) -> io::Result<Bitmap> {
+    let glyphs = text.chars()
+        .map(|c| font.glyphs().iter().find(|(g, _)| *g == c).ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidInput, format!("No glyph for {:?}", c))))
+        .collect::<io::Result<Vec<&Glyph>>>()?;
+
+    let width = glyphs.last().map(|(_, rows)| (glyphs.len() - 1) * font.pitch() + rows[0].len()).unwrap_or(0);
+    let mut bitmap = Bitmap::new(width, font.height(), 0);
+    let mut left = 0;
+    for (_, rows) in glyphs {
+        for (y, row) in rows.iter().enumerate() {
+            for (x, pixel) in row.bytes().enumerate() {
+                if pixel == b'#' {
+                    bitmap[Point::new((left + x) as isize, y as isize)] = 1;
+                }
+            }
+        }
+        left += font.pitch();
+    }
+    Ok(bitmap)
+}
+
+#[test]
+fn test_recognize() {
+    let bitmap = Bitmap::from_text(
+       ".##..###..#..#.
+        #..#.#..#.#..#.
+        #..#.###..####.
+        ####.#..#.#..#.
+        #..#.#..#.#..#.
+        #..#.###..#..#.", &['.', '#']).unwrap();
+    assert_eq!(recognize(&bitmap, 1).unwrap(), "ABH");
+
+    // Inverted ink, with a blank border
+    let bitmap = Bitmap::from_text(
+       "######
+        #....#
+        ####.#
+        ###.##
+        ##.###
+        #.####
+        #....#
+        ######", &['#', '.']).unwrap();
+    assert_eq!(recognize(&bitmap, 1).unwrap(), "Z");
+
+    assert_eq!(recognize(&Bitmap::new(4, 6, 0), 1).unwrap(), "");
+
+    // Y fills its whole cell, so nothing separates it from the next letter
+    let bitmap = Bitmap::from_text(
+       "#...#.##.
+        #...##..#
+        .#.#.#..#
+        ..#..####
+        ..#..#..#
+        ..#..#..#", &['.', '#']).unwrap();
+    assert_eq!(recognize(&bitmap, 1).unwrap(), "YA");
+    assert_eq!(render("YA", Font::Small).unwrap(), bitmap);
+    assert!(recognize(&Bitmap::from_text("#.#\n.#.", &['.', '#']).unwrap(), 1).is_err());
+}
+
+#[test]
+fn test_recognize_all_glyphs() {
+    for &font in [Font::Small, Font::Large].iter() {
+        let text = font.glyphs().iter().map(|(c, _)| c).collect::<String>();
+        let bitmap = render(&text, font).unwrap();
+        assert_eq!(bitmap.height, font.height());
+        assert_eq!(recognize(&bitmap, 1).unwrap(), text);
+    }
+    assert!(render("a", Font::Small).is_err());
+}
@@ -0,0 +1,619 @@
+#[cfg(test)]
+use crate::rng::Xorshift;
+
+use std::collections::HashMap;
+use std::io;
+
+// Objects in orbit around each other, where every object but the root orbits exactly one parent.
+// Names are interned so that objects are referred to by index
+#[derive(Clone, Debug)]
+pub struct OrbitTree {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    root: usize,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl OrbitTree {
+    // Parse lines of "A)B" meaning B orbits A; the root is the one object that orbits nothing
+    pub fn parse(input: &str) -> io::Result<Self> {
+        OrbitTree::parse_lines(input, None)
+    }
+
+    // Like parse, but any object other than root that orbits nothing is an error
+    pub fn parse_with_root(input: &str, root: &str) -> io::Result<Self> {
+        OrbitTree::parse_lines(input, Some(root))
+    }
+
+    fn parse_lines(input: &str, root: Option<&str>) -> io::Result<Self> {
+        let mut tree = OrbitTree {names: Vec::new(), ids: HashMap::new(), parents: Vec::new(), children: Vec::new(), root: 0};
+        let mut first_seen = Vec::new(); // Line where each object first appears
+        for (row, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (parent, child) = line.split_once(')')
+                .filter(|(parent, child)| !parent.is_empty() && !child.is_empty() && !child.contains(')'))
+                .ok_or_else(|| invalid(format!("Expected A)B at line {} but got {:?}", row + 1, line)))?;
+            let parent = tree.intern(parent, row + 1, &mut first_seen);
+            let child = tree.intern(child, row + 1, &mut first_seen);
+            if let Some(old) = tree.parents[child] {
+                return Err(invalid(format!("{} at line {} already orbits {}", tree.names[child], row + 1, tree.names[old])));
+            }
+            tree.parents[child] = Some(parent);
+            tree.children[parent].push(child);
+        }
+
+        let mut roots = (0..tree.len()).filter(|&id| tree.parents[id].is_none());
+        tree.root = match root {
+            Some(root) => {
+                let root = tree.id(root).ok_or_else(|| invalid(format!("No object named {}", root)))?;
+                match roots.find(|&id| id != root) {
+                    Some(id) => return Err(invalid(format!("Unknown parent {} at line {}", tree.names[id], first_seen[id]))),
+                    None if tree.parents[root].is_some() => return Err(invalid(format!("Root {} orbits {}",
+                        tree.names[root], tree.names[tree.parents[root].unwrap()]))),
+                    None => root,
+                }
+            }
+            None => match (roots.next(), roots.next()) {
+                (Some(root), None) => root,
+                (Some(a), Some(b)) => return Err(invalid(format!("Both {} and {} orbit nothing", tree.names[a], tree.names[b]))),
+                (None, _) if tree.is_empty() => return Err(invalid("No orbits".to_string())),
+                (None, _) => return Err(invalid("Every object orbits another, so there is a cycle".to_string())),
+            },
+        };
+
+        // With one parent each, objects that can't be reached from the root must be on a cycle
+        let mut reached = vec![false; tree.len()];
+        let mut stack = vec![tree.root];
+        while let Some(id) = stack.pop() {
+            reached[id] = true;
+            stack.extend(tree.children[id].iter().cloned());
+        }
+        if let Some(id) = reached.iter().position(|&r| !r) {
+            return Err(invalid(format!("{} is on a cycle of orbits", tree.names[id])));
+        }
+        Ok(tree)
+    }
+
+    fn intern(&mut self, name: &str, line: usize, first_seen: &mut Vec<usize>) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.parents.push(None);
+        self.children.push(Vec::new());
+        first_seen.push(line);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    pub fn parent(&self, id: usize) -> Option<usize> {
+        self.parents[id]
+    }
+
+    pub fn children(&self, id: usize) -> &[usize] {
+        &self.children[id]
+    }
+
+    // Every object after the one it orbits, breadth first from the root
+    pub fn topological_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.len());
+        order.push(self.root);
+        let mut i = 0;
+        while i < order.len() {
+            order.extend(self.children[order[i]].iter().cloned());
+            i += 1;
+        }
+        order
+    }
+
+    // Number of objects each object orbits directly and indirectly, without recursing so any depth works
+    pub fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.len()];
+        for id in self.topological_order().into_iter().skip(1) {
+            depths[id] = depths[self.parents[id].unwrap()] + 1;
+        }
+        depths
+    }
+}
+
+#[test]
+fn test_parse() {
+    let tree = OrbitTree::parse("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN").unwrap();
+    assert_eq!(tree.len(), 14);
+    assert_eq!(tree.name(tree.root()), "COM");
+    assert_eq!(tree.parent(tree.root()), None);
+    let b = tree.id("B").unwrap();
+    assert_eq!(tree.parent(b), Some(tree.root()));
+    assert_eq!(tree.children(b).iter().map(|&c| tree.name(c)).collect::<Vec<&str>>(), &["C", "G"]);
+    assert_eq!(tree.id("YOU").map(|id| tree.name(id)), Some("YOU"));
+    assert_eq!(tree.id("NOPE"), None);
+
+    // Names can be any length, and lines can come in any order
+    let tree = OrbitTree::parse("  Sun)Earth\nGalactic Center)Sun\nEarth)Moon\n\n").unwrap();
+    assert_eq!(tree.name(tree.root()), "Galactic Center");
+    assert_eq!(tree.parent(tree.id("Moon").unwrap()), tree.id("Earth"));
+    assert_eq!(OrbitTree::parse_with_root("Sun)Earth\nCOM)Sun", "COM").unwrap().name(0), "Sun");
+}
+
+#[test]
+fn test_depths() {
+    let tree = OrbitTree::parse("B)C\nCOM)B\nB)D\nD)E").unwrap();
+    let order = tree.topological_order().iter().map(|&id| tree.name(id)).collect::<Vec<&str>>();
+    assert_eq!(order, &["COM", "B", "C", "D", "E"]);
+    let depths = tree.depths();
+    assert_eq!(["COM", "B", "C", "D", "E"].iter().map(|n| depths[tree.id(n).unwrap()]).collect::<Vec<usize>>(), &[0, 1, 2, 2, 3]);
+}
+
+// Orbits of a chain of depth objects below COM with width objects around the end of the chain,
+// then YOU also around the end of the chain and SAN around COM
+pub fn generate_orbits(depth: usize, width: usize) -> String {
+    let name = |i: usize| if i == 0 { "COM".to_string() } else { format!("O{}", i) };
+    let mut lines = (1..=depth).map(|i| format!("{}){}", name(i - 1), name(i))).collect::<Vec<String>>();
+    lines.extend((0..width).map(|i| format!("{})F{}", name(depth), i)));
+    lines.push(format!("{})YOU", name(depth)));
+    lines.push("COM)SAN".to_string());
+    lines.join("\n")
+}
+
+#[test]
+fn test_generate_orbits() {
+    // Every object but the end of the chain and COM has at most one object around it
+    for &(depth, width) in [(0, 0), (1, 3), (50, 0), (50, 4)].iter() {
+        let tree = OrbitTree::parse(&generate_orbits(depth, width)).unwrap();
+        assert_eq!(tree.len(), 1 + depth + width + 2);
+        let end = tree.parent(tree.id("YOU").unwrap()).unwrap();
+        assert_eq!(tree.depths()[end], depth);
+        assert_eq!(tree.children(end).len(), width + 1 + if depth == 0 { 1 } else { 0 });
+        assert_eq!(tree.parent(tree.id("SAN").unwrap()), Some(tree.root()));
+        let branching = (0..tree.len()).filter(|&id| tree.children(id).len() > 1).count();
+        assert!(branching <= 2);
+    }
+}
+
+#[test]
+fn test_pathological_orbits() {
+    // Far deeper than the stack would allow recursing through
+    let depth = 100_000;
+    let tree = OrbitTree::parse(&generate_orbits(depth, 0)).unwrap();
+    let depths = tree.depths();
+    assert_eq!(depths.iter().sum::<usize>(), depth * (depth + 1) / 2 + depth + 1 + 1);
+    let index = OrbitIndex::new(&tree);
+    let (you, san) = (tree.id("YOU").unwrap(), tree.id("SAN").unwrap());
+    assert_eq!(index.depth(you), depth + 1);
+    assert_eq!(index.lca(you, san), tree.root());
+    assert_eq!(index.distance(you, san), depth + 2);
+    assert_eq!(index.subtree_size(tree.root()), depth + 3);
+    assert_eq!(index.ancestor(you, depth / 2).map(|id| tree.name(id)), Some(format!("O{}", depth / 2 + 1)).as_deref());
+
+    // Every object around the same one
+    let width = 100_000;
+    let tree = OrbitTree::parse(&generate_orbits(1, width)).unwrap();
+    assert_eq!(tree.depths().iter().sum::<usize>(), 2 * width + 1 + 2 + 1);
+    let index = OrbitIndex::new(&tree);
+    assert_eq!(index.subtree_size(tree.id("O1").unwrap()), width + 2);
+    assert_eq!(index.distance(tree.id("F0").unwrap(), tree.id(&format!("F{}", width - 1)).unwrap()), 2);
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |input| OrbitTree::parse(input).unwrap_err().to_string();
+    assert_eq!(error("COM)B\nB"), "Expected A)B at line 2 but got \"B\"");
+    assert_eq!(error("COM)B\n)C"), "Expected A)B at line 2 but got \")C\"");
+    assert_eq!(error("COM)B)C"), "Expected A)B at line 1 but got \"COM)B)C\"");
+    assert_eq!(error("COM)B\nB)C\nCOM)C"), "C at line 3 already orbits B");
+    assert_eq!(error("COM)B\nX)C"), "Both COM and X orbit nothing");
+    assert_eq!(error("A)B\nB)A"), "Every object orbits another, so there is a cycle");
+    assert_eq!(error("COM)B\nC)D\nD)C"), "C is on a cycle of orbits");
+    assert_eq!(error(""), "No orbits");
+
+    let error = |input, root| OrbitTree::parse_with_root(input, root).unwrap_err().to_string();
+    assert_eq!(error("COM)B\nB)C\nX)D", "COM"), "Unknown parent X at line 3");
+    assert_eq!(error("COM)B", "ROOT"), "No object named ROOT");
+    assert_eq!(error("COM)B", "B"), "Unknown parent COM at line 1");
+}
+
+// Answers ancestry queries about a tree after one pass over it, using jumps[k][id] as the
+// ancestor 2^k levels above id (or the root, when that would be above it)
+pub struct OrbitIndex<'a> {
+    tree: &'a OrbitTree,
+    depths: Vec<usize>,
+    sizes: Vec<usize>,
+    jumps: Vec<Vec<usize>>,
+}
+
+impl<'a> OrbitIndex<'a> {
+    pub fn new(tree: &'a OrbitTree) -> Self {
+        let order = tree.topological_order();
+        let depths = tree.depths();
+        let mut sizes = vec![1; tree.len()];
+        for &id in order.iter().rev() {
+            if let Some(parent) = tree.parents[id] {
+                sizes[parent] += sizes[id];
+            }
+        }
+
+        let height = depths.iter().max().cloned().unwrap_or(0);
+        let mut jumps = vec![(0..tree.len()).map(|id| tree.parents[id].unwrap_or(id)).collect::<Vec<usize>>()];
+        while 1 << jumps.len() <= height {
+            let last = jumps.last().unwrap();
+            let next = last.iter().map(|&id| last[id]).collect();
+            jumps.push(next);
+        }
+        OrbitIndex {tree, depths, sizes, jumps}
+    }
+
+    pub fn tree(&self) -> &'a OrbitTree {
+        self.tree
+    }
+
+    // Number of objects id orbits directly and indirectly
+    pub fn depth(&self, id: usize) -> usize {
+        self.depths[id]
+    }
+
+    // Number of objects in orbit around id directly or indirectly, plus id itself
+    pub fn subtree_size(&self, id: usize) -> usize {
+        self.sizes[id]
+    }
+
+    pub fn ancestor(&self, mut id: usize, levels: usize) -> Option<usize> {
+        if levels > self.depths[id] {
+            return None;
+        }
+        for (k, jump) in self.jumps.iter().enumerate() {
+            if levels & 1 << k != 0 {
+                id = jump[id];
+            }
+        }
+        Some(id)
+    }
+
+    // Lowest common ancestor, which may be a or b itself
+    pub fn lca(&self, a: usize, b: usize) -> usize {
+        let (a, b) = if self.depths[a] >= self.depths[b] { (a, b) } else { (b, a) };
+        let mut a = self.ancestor(a, self.depths[a] - self.depths[b]).unwrap();
+        let mut b = b;
+        if a == b {
+            return a;
+        }
+        for jump in self.jumps.iter().rev() {
+            if jump[a] != jump[b] {
+                a = jump[a];
+                b = jump[b];
+            }
+        }
+        self.jumps[0][a]
+    }
+
+    // Orbital transfers needed to go from a to b
+    pub fn distance(&self, a: usize, b: usize) -> usize {
+        self.depths[a] + self.depths[b] - 2 * self.depths[self.lca(a, b)]
+    }
+
+    // Objects visited going from a to b, including both
+    pub fn path(&self, a: usize, b: usize) -> Vec<usize> {
+        let lca = self.lca(a, b);
+        let up = |mut id: usize| {
+            let mut path = vec![id];
+            while id != lca {
+                id = self.jumps[0][id];
+                path.push(id);
+            }
+            path
+        };
+        let mut path = up(a);
+        let mut down = up(b);
+        down.pop();
+        path.extend(down.into_iter().rev());
+        path
+    }
+}
+
+#[test]
+fn test_orbit_index() {
+    let tree = OrbitTree::parse("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN").unwrap();
+    let index = OrbitIndex::new(&tree);
+    let id = |name| tree.id(name).unwrap();
+    let names = |path: Vec<usize>| path.iter().map(|&id| tree.name(id)).collect::<Vec<&str>>().join(",");
+
+    assert_eq!(index.depth(id("COM")), 0);
+    assert_eq!(index.depth(id("L")), 7);
+    assert_eq!((0..tree.len()).map(|i| index.depth(i)).sum::<usize>(), 54);
+    assert_eq!(index.subtree_size(id("COM")), 14);
+    assert_eq!(index.subtree_size(id("D")), 9);
+    assert_eq!(index.subtree_size(id("SAN")), 1);
+    assert_eq!(index.ancestor(id("L"), 2), Some(id("J")));
+    assert_eq!(index.ancestor(id("L"), 7), Some(id("COM")));
+    assert_eq!(index.ancestor(id("L"), 8), None);
+
+    assert_eq!(index.lca(id("YOU"), id("SAN")), id("D"));
+    assert_eq!(index.lca(id("H"), id("L")), id("B"));
+    assert_eq!(index.lca(id("E"), id("L")), id("E"));
+    assert_eq!(index.lca(id("F"), id("F")), id("F"));
+    assert_eq!(index.distance(id("K"), id("I")), 4);
+    assert_eq!(index.distance(tree.parent(id("YOU")).unwrap(), tree.parent(id("SAN")).unwrap()), 4);
+    assert_eq!(names(index.path(id("K"), id("I"))), "K,J,E,D,I");
+    assert_eq!(names(index.path(id("D"), id("L"))), "D,E,J,K,L");
+    assert_eq!(names(index.path(id("H"), id("H"))), "H");
+}
+
+#[test]
+fn test_orbit_index_random() {
+    // Each object orbits a random earlier one, checked against walking up from both objects
+    let mut rng = Xorshift::new(3);
+    let mut next = move || rng.next_u64() as usize;
+    let input = (1..500).map(|i| format!("{}){}", next() % i, i)).collect::<Vec<String>>().join("\n");
+    let tree = OrbitTree::parse(&input).unwrap();
+    let index = OrbitIndex::new(&tree);
+    let ancestors = |mut id| {
+        let mut path = vec![id];
+        while let Some(parent) = tree.parent(id) {
+            path.push(parent);
+            id = parent;
+        }
+        path
+    };
+    for _ in 0..1000 {
+        let (a, b) = (next() % tree.len(), next() % tree.len());
+        let (up_a, up_b) = (ancestors(a), ancestors(b));
+        let lca = *up_a.iter().find(|id| up_b.contains(id)).unwrap();
+        assert_eq!(index.lca(a, b), lca);
+        assert_eq!(index.depth(a), up_a.len() - 1);
+        assert_eq!(index.path(a, b).len(), index.distance(a, b) + 1);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    pub highlight: Vec<usize>, // Objects to point out, such as a transfer path
+    pub collapse: usize, // Show unbranched chains of at least this many objects as one entry, unless below 2
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Shown {
+    Object(usize),
+    Chain(Vec<usize>), // Collapsed objects, each the only one orbiting the previous one
+}
+
+// Position of something shown in the preorder of the exported tree
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Entry {
+    shown: Shown,
+    parent: Option<usize>, // Index of the parent's entry
+    depth: usize,
+    last: bool, // Whether this is the last entry under its parent
+}
+
+impl OrbitTree {
+    // The only object orbiting id, if there is exactly one
+    fn chain_link(&self, id: usize) -> Option<usize> {
+        match self.children[id].as_slice() {
+            &[child] => Some(child),
+            _ => None,
+        }
+    }
+
+    // Walk the tree in preorder with an explicit stack, so that deep chains are fine. Each chain is followed once,
+    // from its first object, and objects in a chain too short to fold are marked so they don't follow it again
+    fn entries(&self, collapse: usize) -> Vec<Entry> {
+        let mut entries = Vec::with_capacity(self.len());
+        let mut stack = vec![(self.root, None, 0, true, false)];
+        while let Some((mut id, mut parent, mut depth, mut last, mut in_run)) = stack.pop() {
+            // The root keeps its name even when only one object orbits it, and a single object is shown as itself
+            if collapse >= 2 && !in_run && parent.is_some() {
+                let mut run = vec![];
+                let mut next = id;
+                while let Some(child) = self.chain_link(next) {
+                    run.push(next);
+                    next = child;
+                }
+                if run.len() >= collapse {
+                    entries.push(Entry {shown: Shown::Chain(run), parent, depth, last});
+                    parent = Some(entries.len() - 1);
+                    depth += 1;
+                    last = true;
+                    id = next;
+                } else {
+                    in_run = true;
+                }
+            }
+            entries.push(Entry {shown: Shown::Object(id), parent, depth, last});
+            let index = entries.len() - 1;
+            let children = &self.children[id];
+            for (i, &child) in children.iter().enumerate().rev() {
+                stack.push((child, Some(index), depth + 1, i + 1 == children.len(), in_run && children.len() == 1));
+            }
+        }
+        entries
+    }
+
+    fn label(&self, shown: &Shown) -> String {
+        match shown {
+            Shown::Object(id) => self.names[*id].clone(),
+            Shown::Chain(run) => format!("[{} objects: {}..{}]", run.len(),
+                self.names[run[0]], self.names[run[run.len() - 1]]),
+        }
+    }
+
+    fn is_highlighted(shown: &Shown, highlight: &[bool]) -> bool {
+        match shown {
+            Shown::Object(id) => highlight[*id],
+            Shown::Chain(run) => run.iter().any(|&id| highlight[id]),
+        }
+    }
+
+    fn highlights(&self, options: &ExportOptions) -> Vec<bool> {
+        let mut highlight = vec![false; self.len()];
+        options.highlight.iter().for_each(|&id| highlight[id] = true);
+        highlight
+    }
+
+    // Indented tree with ASCII branches, marking highlighted entries with *
+    pub fn to_text(&self, options: &ExportOptions) -> String {
+        let highlight = self.highlights(options);
+        let mut text = String::new();
+        let mut open = Vec::new(); // Whether each level above still has entries to come
+        for entry in self.entries(options.collapse) {
+            open.truncate(entry.depth.saturating_sub(1));
+            for &more in open.iter() {
+                text += if more { "|   " } else { "    " };
+            }
+            if entry.depth > 0 {
+                text += if entry.last { "`-- " } else { "|-- " };
+                open.push(!entry.last);
+            }
+            text += &self.label(&entry.shown);
+            if OrbitTree::is_highlighted(&entry.shown, &highlight) {
+                text += " *";
+            }
+            text += "\n";
+        }
+        text
+    }
+
+    // Graphviz digraph with an edge from each object to everything orbiting it, and highlighted entries in red
+    pub fn to_dot(&self, options: &ExportOptions) -> String {
+        let highlight = self.highlights(options);
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let entries = self.entries(options.collapse);
+        // Collapsed chains need names that can't clash with objects
+        let node = |i: usize| match &entries[i].shown {
+            Shown::Object(id) => quote(&self.names[*id]),
+            Shown::Chain(_) => format!("chain{}", i),
+        };
+
+        let mut dot = "digraph orbits {\n    rankdir=LR;\n".to_string();
+        for (i, entry) in entries.iter().enumerate() {
+            let highlighted = OrbitTree::is_highlighted(&entry.shown, &highlight);
+            let mut style = vec![];
+            if let Shown::Chain(_) = entry.shown {
+                style.push(format!("label={}, shape=box, style=dashed", quote(&self.label(&entry.shown))));
+            }
+            if highlighted {
+                style.push("color=red, fontcolor=red".to_string());
+            }
+            if !style.is_empty() || entry.parent.is_none() {
+                dot += &format!("    {} [{}];\n", node(i), style.join(", "));
+            }
+            if let Some(parent) = entry.parent {
+                let both = highlighted && OrbitTree::is_highlighted(&entries[parent].shown, &highlight);
+                let style = if both { " [color=red, penwidth=2]" } else { "" };
+                dot += &format!("    {} -> {}{};\n", node(parent), node(i), style);
+            }
+        }
+        dot + "}\n"
+    }
+}
+
+#[test]
+fn test_to_text() {
+    let tree = OrbitTree::parse("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN").unwrap();
+    let index = OrbitIndex::new(&tree);
+    let path = index.path(tree.id("YOU").unwrap(), tree.id("SAN").unwrap());
+    let options = ExportOptions {highlight: path, collapse: 0};
+    assert_eq!(tree.to_text(&options), "\
+COM
+`-- B
+    |-- C
+    |   `-- D *
+    |       |-- E *
+    |       |   |-- F
+    |       |   `-- J *
+    |       |       `-- K *
+    |       |           |-- L
+    |       |           `-- YOU *
+    |       `-- I *
+    |           `-- SAN *
+    `-- G
+        `-- H
+");
+
+    // C, G, I and J are each orbited by exactly one object, which is too short a chain to fold
+    for collapse in 1..=2 {
+        assert_eq!(tree.to_text(&ExportOptions {highlight: vec![], collapse}), tree.to_text(&ExportOptions::default()));
+    }
+    let tree = OrbitTree::parse("COM)A\nA)B\nB)C\nC)D\nC)E").unwrap();
+    assert_eq!(tree.to_text(&ExportOptions {highlight: vec![], collapse: 2}), "\
+COM
+`-- [2 objects: A..B]
+    `-- C
+        |-- D
+        `-- E
+");
+}
+
+#[test]
+fn test_to_dot() {
+    let tree = OrbitTree::parse("COM)B\nB)C\nC)D\nD)E\nB)\"Q\"").unwrap();
+    let options = ExportOptions {highlight: vec![tree.id("C").unwrap(), tree.id("D").unwrap()], collapse: 0};
+    assert_eq!(tree.to_dot(&options), "\
+digraph orbits {
+    rankdir=LR;
+    \"COM\" [];
+    \"COM\" -> \"B\";
+    \"C\" [color=red, fontcolor=red];
+    \"B\" -> \"C\";
+    \"D\" [color=red, fontcolor=red];
+    \"C\" -> \"D\" [color=red, penwidth=2];
+    \"D\" -> \"E\";
+    \"B\" -> \"\\\"Q\\\"\";
+}
+");
+
+    let tree = OrbitTree::parse("COM)A\nA)B\nB)C\nC)D\nD)E\nD)F\nCOM)G").unwrap();
+    let dot = tree.to_dot(&ExportOptions {highlight: vec![tree.id("B").unwrap()], collapse: 2});
+    assert!(dot.contains("    chain1 [label=\"[3 objects: A..C]\", shape=box, style=dashed, color=red, fontcolor=red];\n"));
+    assert!(dot.contains("    \"COM\" -> chain1;\n    chain1 -> \"D\";\n"));
+}
+
+#[test]
+fn test_export_deep_chain() {
+    let tree = OrbitTree::parse(&generate_orbits(100_000, 2)).unwrap();
+    let options = ExportOptions {highlight: vec![], collapse: 3};
+    assert_eq!(tree.to_text(&options), "\
+COM
+|-- [99999 objects: O1..O99999]
+|   `-- O100000
+|       |-- F0
+|       |-- F1
+|       `-- YOU
+`-- SAN
+");
+    assert_eq!(tree.to_dot(&options).lines().count(), 11);
+
+    // Without folding, the root gets a node and every other object an edge
+    let dot = tree.to_dot(&ExportOptions::default());
+    assert_eq!(dot.lines().count(), 3 + 1 + 100_004);
+    assert!(dot.contains("    \"O99999\" -> \"O100000\";\n"));
+    // A chain too short to fold is followed once rather than from each of its objects
+    assert_eq!(tree.to_dot(&ExportOptions {highlight: vec![], collapse: 100_001}), dot);
+}
@@ -0,0 +1,301 @@
+use crate::grid::Grid;
+#[cfg(test)]
+use crate::grid::Point;
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+
+pub type Color = [u8; 3];
+
+pub const BLACK_AND_WHITE: [Color; 2] = [[0, 0, 0], [255, 255, 255]];
+
+// Row-major image of palette indices
+pub type Bitmap = Grid<u8>;
+
+impl Bitmap {
+    // Render one character per pixel, looking up each pixel value in glyphs
+    pub fn to_text(&self, glyphs: &[char]) -> String {
+        self.render(|&p| glyphs.get(p as usize).cloned().unwrap_or('?'))
+    }
+
+    // Inverse of to_text; surrounding whitespace on each line is ignored
+    pub fn from_text(input: &str, glyphs: &[char]) -> io::Result<Self> {
+        Grid::parse(input, |c| glyphs.iter().position(|&g| g == c).map(|p| p as u8))
+    }
+}
+
+#[test]
+fn test_bitmap() {
+    let mut bitmap = Bitmap::new(3, 2, 0);
+    bitmap[Point::new(1, 0)] = 1;
+    bitmap[Point::new(2, 1)] = 1;
+    assert_eq!(bitmap.cells, &[0, 1, 0, 0, 0, 1]);
+    assert_eq!(bitmap.to_text(&['.', '#']), ".#.\n..#");
+    assert_eq!(Bitmap::from_text(" .#.\n ..#", &['.', '#']).unwrap(), bitmap);
+    assert!(Bitmap::from_text(".#.\n.#", &['.', '#']).is_err());
+    assert!(Bitmap::from_text(".x.", &['.', '#']).is_err());
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub scale: usize,
+    pub palette: Vec<Color>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {scale: 1, palette: BLACK_AND_WHITE.to_vec()}
+    }
+}
+
+impl RenderOptions {
+    // Build options from optional command line strings, e.g. "4" and "000000,ffffff"
+    pub fn parse(scale: Option<&str>, palette: Option<&str>) -> io::Result<Self> {
+        let mut options = RenderOptions::default();
+        if let Some(scale) = scale {
+            options.scale = scale.trim().parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid scale {}", scale)))?;
+        }
+        if let Some(palette) = palette {
+            options.palette = parse_palette(palette)?;
+        }
+        Ok(options)
+    }
+
+    fn color(&self, value: u8) -> io::Result<Color> {
+        self.palette.get(value as usize).cloned().ok_or_else(||
+            io::Error::new(io::ErrorKind::InvalidData, format!("No palette entry for pixel {}", value)))
+    }
+}
+
+// Parse str "000000,ff8000" into vec![[0, 0, 0], [255, 128, 0]]
+pub fn parse_palette(input: &str) -> io::Result<Vec<Color>> {
+    input.trim().split(',')
+        .map(|s| {
+            let s = s.trim().trim_start_matches('#');
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid color {}", s));
+            if s.len() != 6 || !s.is_ascii() {
+                return Err(invalid());
+            }
+            let mut color = [0; 3];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+            }
+            Ok(color)
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_palette() {
+    assert_eq!(parse_palette("000000, #FF8000").unwrap(), &[[0, 0, 0], [255, 128, 0]]);
+    assert!(parse_palette("00000").is_err());
+    assert!(parse_palette("00000g").is_err());
+    assert!(RenderOptions::parse(Some("x"), None).is_err());
+    assert_eq!(RenderOptions::parse(Some("3"), None).unwrap().scale, 3);
+}
+
+fn luma(color: Color) -> u8 {
+    ((299 * color[0] as u32 + 587 * color[1] as u32 + 114 * color[2] as u32) / 1000) as u8
+}
+
+// Emit each scaled row of colors, so the binary formats only differ in how a color is written
+fn write_scaled<W, F>(out: &mut W, bitmap: &Bitmap, options: &RenderOptions, mut write_color: F) -> io::Result<()>
+    where W: Write, F: FnMut(&mut W, Color) -> io::Result<()>
+{
+    for row in bitmap.rows() {
+        let colors = row.iter().map(|&p| options.color(p)).collect::<io::Result<Vec<Color>>>()?;
+        for _ in 0..options.scale {
+            for &color in colors.iter() {
+                for _ in 0..options.scale {
+                    write_color(out, color)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_ppm<W: Write>(mut out: W, bitmap: &Bitmap, options: &RenderOptions) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", bitmap.width * options.scale, bitmap.height * options.scale)?;
+    write_scaled(&mut out, bitmap, options, |out, color| out.write_all(&color))
+}
+
+#[test]
+fn test_write_ppm() {
+    let bitmap = Bitmap::from_cells(2, 1, vec![1, 0]).unwrap();
+    let options = RenderOptions {scale: 2, ..Default::default()};
+    let mut out = Vec::new();
+    write_ppm(&mut out, &bitmap, &options).unwrap();
+    let mut expected = b"P6\n4 2\n255\n".to_vec();
+    for _ in 0..2 {
+        expected.extend_from_slice(&[255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0]);
+    }
+    assert_eq!(out, expected);
+}
+
+pub fn write_pgm<W: Write>(mut out: W, bitmap: &Bitmap, options: &RenderOptions) -> io::Result<()> {
+    write!(out, "P5\n{} {}\n255\n", bitmap.width * options.scale, bitmap.height * options.scale)?;
+    write_scaled(&mut out, bitmap, options, |out, color| out.write_all(&[luma(color)]))
+}
+
+#[test]
+fn test_write_pgm() {
+    let bitmap = Bitmap::from_cells(3, 1, vec![0, 1, 2]).unwrap();
+    let options = RenderOptions {scale: 1, palette: vec![[0, 0, 0], [255, 255, 255], [255, 0, 0]]};
+    let mut out = Vec::new();
+    write_pgm(&mut out, &bitmap, &options).unwrap();
+    assert_eq!(out, b"P5\n3 1\n255\n\x00\xff\x4c");
+    assert!(write_pgm(Vec::new(), &Bitmap::from_cells(1, 1, vec![3]).unwrap(), &options).is_err());
+}
+
+// One rect per horizontal run of equal pixels; the viewBox keeps pixel units so scale only sets the size
+pub fn write_svg<W: Write>(mut out: W, bitmap: &Bitmap, options: &RenderOptions) -> io::Result<()> {
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">",
+        bitmap.width * options.scale, bitmap.height * options.scale, bitmap.width, bitmap.height)?;
+    for (y, row) in bitmap.rows().enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            let run = row[x..].iter().take_while(|&&p| p == row[x]).count();
+            let color = options.color(row[x])?;
+            writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                x, y, run, color[0], color[1], color[2])?;
+            x += run;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+#[test]
+fn test_write_svg() {
+    let bitmap = Bitmap::from_cells(3, 1, vec![1, 1, 0]).unwrap();
+    let options = RenderOptions {scale: 10, ..Default::default()};
+    let mut out = Vec::new();
+    write_svg(&mut out, &bitmap, &options).unwrap();
+    let svg = String::from_utf8(out).unwrap();
+    assert!(svg.contains("width=\"30\" height=\"10\" viewBox=\"0 0 3 1\""));
+    assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"2\" height=\"1\" fill=\"#ffffff\"/>"));
+    assert!(svg.contains("<rect x=\"2\" y=\"0\" width=\"1\" height=\"1\" fill=\"#000000\"/>"));
+}
+
+// Read a binary PPM or PGM, mapping each color to the closest one in the palette
+pub fn read_pnm<R: Read>(mut input: R, palette: &[Color]) -> io::Result<Bitmap> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    // Magic number, width, height and maximum value, separated by whitespace and comments
+    let mut header = Vec::new();
+    let mut i = 0;
+    while header.len() < 4 {
+        match data.get(i) {
+            Some(b'#') => while i < data.len() && data[i] != b'\n' { i += 1 },
+            Some(c) if c.is_ascii_whitespace() => i += 1,
+            Some(_) => {
+                let start = i;
+                while i < data.len() && !data[i].is_ascii_whitespace() { i += 1 }
+                header.push(String::from_utf8_lossy(&data[start..i]).into_owned());
+            }
+            None => return Err(invalid("Truncated header")),
+        }
+    }
+    let channels = match header[0].as_str() {
+        "P5" => 1,
+        "P6" => 3,
+        _ => return Err(invalid("Expected a binary PGM or PPM")),
+    };
+    let number = |s: &str| s.parse::<usize>().map_err(|_| invalid(&format!("Invalid header value {}", s)));
+    let (width, height, max) = (number(&header[1])?, number(&header[2])?, number(&header[3])?);
+    if max == 0 || max > 255 {
+        return Err(invalid(&format!("Unsupported maximum value {}", max)));
+    }
+
+    // A single whitespace byte separates the header from the pixels
+    let pixels = data.get(i + 1..).unwrap_or(&[]);
+    let size = width.checked_mul(height).and_then(|area| area.checked_mul(channels))
+        .ok_or_else(|| invalid(&format!("Image size {}x{} is too large", width, height)))?;
+    if pixels.len() != size {
+        return Err(invalid(&format!("Expected {} bytes of pixels for {}x{}, got {}", size, width, height, pixels.len())));
+    }
+    if palette.is_empty() {
+        return Err(invalid("Empty palette"));
+    }
+    let cells = pixels.chunks(channels)
+        .map(|pixel| {
+            let channel = |c: usize| (pixel[c % channels] as usize * 255 / max) as i32;
+            let distance = |color: &Color| (0..3).map(|c| (channel(c) - color[c] as i32).pow(2)).sum::<i32>();
+            (0..palette.len()).min_by_key(|&p| distance(&palette[p])).unwrap() as u8
+        })
+        .collect();
+    Bitmap::from_cells(width, height, cells)
+}
+
+#[test]
+fn test_read_pnm() {
+    let bitmap = Bitmap::from_cells(3, 2, vec![0, 1, 2, 2, 1, 0]).unwrap();
+    let options = RenderOptions {scale: 1, palette: vec![[0, 0, 0], [255, 255, 255], [255, 0, 0]]};
+    let mut out = Vec::new();
+    write_ppm(&mut out, &bitmap, &options).unwrap();
+    assert_eq!(read_pnm(&out[..], &options.palette).unwrap(), bitmap);
+
+    // Grays go to the closest entry, and the header can have comments and a smaller maximum
+    let gray = b"P5\n# comment\n3 1 15\n\x00\x0c\x05";
+    assert_eq!(read_pnm(&gray[..], &BLACK_AND_WHITE).unwrap().cells, &[0, 1, 0]);
+    assert!(read_pnm(&b"P5\n3 1 255\n\x00"[..], &BLACK_AND_WHITE).is_err());
+    assert!(read_pnm(&b"P3\n1 1 255\n0 0 0"[..], &BLACK_AND_WHITE).is_err());
+    assert!(read_pnm(&b"P6\n1 1"[..], &BLACK_AND_WHITE).is_err());
+    let huge = format!("P6\n{} {} 255\n\x00", usize::MAX / 2, 2);
+    assert_eq!(read_pnm(huge.as_bytes(), &BLACK_AND_WHITE).unwrap_err().to_string(),
+        format!("Image size {}x2 is too large", usize::MAX / 2));
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Format {
+    Ppm,
+    Pgm,
+    Svg,
+}
+
+impl Format {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("ppm") => Ok(Format::Ppm),
+            Some("pgm") => Ok(Format::Pgm),
+            Some("svg") => Ok(Format::Svg),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Unknown image format for {}", path.display()))),
+        }
+    }
+
+    pub fn write<W: Write>(self, out: W, bitmap: &Bitmap, options: &RenderOptions) -> io::Result<()> {
+        match self {
+            Format::Ppm => write_ppm(out, bitmap, options),
+            Format::Pgm => write_pgm(out, bitmap, options),
+            Format::Svg => write_svg(out, bitmap, options),
+        }
+    }
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(Format::from_path("hull.PPM").unwrap(), Format::Ppm);
+    assert_eq!(Format::from_path("out/image.pgm").unwrap(), Format::Pgm);
+    assert_eq!(Format::from_path("image.svg").unwrap(), Format::Svg);
+    assert!(Format::from_path("image.png").is_err());
+    assert!(Format::from_path("image").is_err());
+}
+
+// Write bitmap to path, choosing the format from the file extension
+pub fn save<P: AsRef<Path>>(path: P, bitmap: &Bitmap, options: &RenderOptions) -> io::Result<()> {
+    let format = Format::from_path(&path)?;
+    let mut out = BufWriter::new(File::create(path)?);
+    format.write(&mut out, bitmap, options)?;
+    out.flush()
+}
+
+// Read a bitmap from a PPM or PGM file, mapping colors to the closest in the palette
+pub fn load<P: AsRef<Path>>(path: P, palette: &[Color]) -> io::Result<Bitmap> {
+    read_pnm(BufReader::new(File::open(path)?), palette)
+}
@@ -0,0 +1,35 @@
+// Xorshift generator, which is plenty for generated inputs and randomized tests that have to be repeatable
+#[derive(Clone, Debug)]
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    // Spread the seed's bits so that nearby seeds give unrelated sequences, and keep the state nonzero
+    pub fn new(seed: u64) -> Self {
+        Xorshift {state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // Number in 0..n, with a bias too small to matter here
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+#[test]
+fn test_xorshift() {
+    let mut rng = Xorshift::new(1);
+    let first = (0..100).map(|_| rng.below(10)).collect::<Vec<u64>>();
+    let mut rng = Xorshift::new(1);
+    assert_eq!((0..100).map(|_| rng.below(10)).collect::<Vec<u64>>(), first);
+    assert!((0..10).all(|d| first.contains(&d)));
+    assert_ne!(Xorshift::new(2).next_u64(), Xorshift::new(1).next_u64());
+    assert_ne!(Xorshift::new(0).next_u64(), 0);
+}
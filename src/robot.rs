@@ -0,0 +1,153 @@
+use crate::grid::{Direction, Point, SparseGrid, Turn};
+use crate::intcode::Intcode;
+
+use std::io;
+
+pub type HullMap = SparseGrid<isize>;
+
+// 0 means turn left 90 degrees, and 1 means turn right 90 degrees
+pub fn parse_turn(code: isize) -> io::Result<Turn> {
+    match code {
+        0 => Ok(Turn::Left),
+        1 => Ok(Turn::Right),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("Invalid turn {}", code))),
+    }
+}
+
+#[test]
+fn test_parse_turn() {
+    assert_eq!(parse_turn(0).unwrap(), Turn::Left);
+    assert_eq!(parse_turn(1).unwrap(), Turn::Right);
+    assert!(parse_turn(2).is_err());
+}
+
+// Decides what to paint and where to turn given the color of the current panel;
+// returns None once the controller has nothing more to say
+pub trait Controller {
+    fn step(&mut self, color: isize) -> io::Result<Option<(isize, Turn)>>;
+}
+
+impl<F> Controller for F where F: FnMut(isize) -> Option<(isize, Turn)> {
+    fn step(&mut self, color: isize) -> io::Result<Option<(isize, Turn)>> {
+        Ok(self(color))
+    }
+}
+
+pub struct IntcodeController {
+    program: Intcode,
+    running: bool,
+}
+
+impl IntcodeController {
+    pub fn new(memory: &[isize]) -> io::Result<Self> {
+        let mut program = Intcode::new(memory);
+        let running = program.run()?;
+        Ok(IntcodeController {program, running})
+    }
+}
+
+impl Controller for IntcodeController {
+    fn step(&mut self, color: isize) -> io::Result<Option<(isize, Turn)>> {
+        if !self.running || !self.program.resume(color)? {
+            self.running = false;
+            return Ok(None);
+        }
+
+        let turn = self.program.output.pop();
+        let color = self.program.output.pop();
+        match (color, turn) {
+            (Some(color), Some(turn)) => Ok(Some((color, parse_turn(turn)?))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+                "Expected color and turn from program")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub position: Point, // Panel that was painted
+    pub color: isize,
+    pub heading: Direction, // Heading after turning
+}
+
+pub struct Robot {
+    pub position: Point,
+    pub heading: Direction,
+    pub hull: HullMap,
+    pub moves: Vec<Move>,
+    pub record: bool, // Only log moves when set
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Robot::new()
+    }
+}
+
+impl Robot {
+    // The robot starts at the origin facing up, on a hull of black panels
+    pub fn new() -> Self {
+        Robot {position: Point::ORIGIN, heading: Direction::Up, hull: SparseGrid::new(), moves: Vec::new(), record: false}
+    }
+
+    pub fn with_panels(panels: &[(Point, isize)]) -> Self {
+        let mut robot = Robot::new();
+        robot.hull.extend(panels.iter().cloned());
+        robot
+    }
+
+    pub fn color(&self) -> isize {
+        self.hull.get(self.position).cloned().unwrap_or(0)
+    }
+
+    // Paint the current panel, then turn and move forward exactly one panel
+    pub fn step(&mut self, color: isize, turn: Turn) {
+        self.hull.insert(self.position, color);
+        self.heading = self.heading.turn(turn);
+        if self.record {
+            self.moves.push(Move {position: self.position, color, heading: self.heading});
+        }
+        self.position = self.heading.step(self.position);
+    }
+
+    pub fn run<C: Controller>(&mut self, controller: &mut C) -> io::Result<()> {
+        while let Some((color, turn)) = controller.step(self.color())? {
+            self.step(color, turn);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_robot() {
+    let mut script = vec![(1, Turn::Left), (0, Turn::Left), (1, Turn::Left), (1, Turn::Left),
+        (0, Turn::Right), (1, Turn::Left), (1, Turn::Left)].into_iter();
+    let mut seen = vec![];
+    let mut controller = |color| {
+        seen.push(color);
+        script.next()
+    };
+
+    let mut robot = Robot::new();
+    robot.record = true;
+    robot.run(&mut controller).unwrap();
+
+    assert_eq!(seen, &[0, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(robot.hull.len(), 6);
+    assert_eq!(robot.position, Point::new(0, -1));
+    assert_eq!(robot.heading, Direction::Left);
+    assert_eq!(robot.moves.len(), 7);
+    assert_eq!(robot.moves[4], Move {position: Point::ORIGIN, color: 0, heading: Direction::Right});
+}
+
+#[test]
+fn test_robot_with_panels() {
+    let mut robot = Robot::with_panels(&[(Point::ORIGIN, 1)]);
+    assert_eq!(robot.color(), 1);
+    robot.step(0, Turn::Right);
+    assert_eq!(robot.color(), 0);
+    assert_eq!(robot.hull[Point::ORIGIN], 0);
+    assert_eq!(robot.position, Point::new(1, 0));
+    assert!(robot.moves.is_empty());
+}
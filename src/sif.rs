@@ -0,0 +1,226 @@
+use crate::grid::Grid;
+#[cfg(test)]
+use crate::ocr::{self, Font};
+use crate::raster::Bitmap;
+use crate::rng::Xorshift;
+
+use std::cmp::Ordering;
+use std::io;
+
+// Space Image Format: layers of width x height digits, with the first layer in front
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SpaceImage {
+    pub width: usize,
+    pub height: usize,
+    pub layers: Vec<Grid<u8>>,
+}
+
+impl SpaceImage {
+    // Surrounding whitespace is ignored, but every layer must be complete
+    pub fn parse(input: &str, width: usize, height: usize) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let pixels = width * height;
+        if pixels == 0 {
+            return Err(invalid(format!("Layers can't be {}x{}", width, height)));
+        }
+        let digits = input.trim().chars().enumerate()
+            .map(|(i, c)| c.to_digit(10).map(|d| d as u8)
+                .ok_or_else(|| invalid(format!("Invalid digit {:?} at position {}", c, i))))
+            .collect::<io::Result<Vec<u8>>>()?;
+        if digits.is_empty() {
+            return Err(invalid("No layers".to_string()));
+        }
+        if digits.len() % pixels != 0 {
+            return Err(invalid(format!("Expected whole {}x{} layers of {} digits, but the last of {} has {}",
+                width, height, pixels, digits.len() / pixels + 1, digits.len() % pixels)));
+        }
+        let layers = digits.chunks(pixels)
+            .map(|layer| Grid::from_cells(width, height, layer.to_vec()))
+            .collect::<io::Result<Vec<Grid<u8>>>>()?;
+        Ok(SpaceImage {width, height, layers})
+    }
+
+    // How many of each digit every layer has
+    pub fn histograms(&self) -> Vec<[usize; 10]> {
+        self.layers.iter()
+            .map(|layer| layer.cells.iter().fold([0; 10], |mut counts, &digit| {
+                counts[digit as usize] += 1;
+                counts
+            }))
+            .collect()
+    }
+
+    // Composite layers front to back, showing the first opaque digit behind each pixel
+    pub fn decode(&self, semantics: &Semantics) -> io::Result<Bitmap> {
+        let image = Grid::from_fn(self.width, self.height, |p| self.layers.iter()
+            .map(|layer| layer[p])
+            .find(|&digit| !semantics.is_transparent(digit))
+            .map(|digit| semantics.colors[digit as usize])
+            .or(semantics.background)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                format!("Invalid pixel ({}, {})", p.x, p.y))));
+
+        Bitmap::from_cells(self.width, self.height, image.cells.into_iter().collect::<io::Result<Vec<u8>>>()?)
+    }
+}
+
+#[test]
+fn test_parse() {
+    let image = SpaceImage::parse("123456789012\n", 3, 2).unwrap();
+    assert_eq!(image.layers.len(), 2);
+    assert_eq!(image.layers[1].cells, &[7, 8, 9, 0, 1, 2]);
+    assert_eq!(image.histograms(), vec![[0, 1, 1, 1, 1, 1, 1, 0, 0, 0], [1, 1, 1, 0, 0, 0, 0, 1, 1, 1]]);
+
+    let error = SpaceImage::parse("1234567", 3, 2).unwrap_err();
+    assert_eq!(error.to_string(), "Expected whole 3x2 layers of 6 digits, but the last of 2 has 1");
+    assert!(SpaceImage::parse("12x456", 3, 2).is_err());
+    assert!(SpaceImage::parse("", 3, 2).is_err());
+    assert!(SpaceImage::parse("123", 0, 2).is_err());
+}
+
+// What each digit means when compositing layers
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Semantics {
+    pub transparent: u16, // Bit mask of digits that show the layers behind them
+    pub colors: [u8; 10], // Pixel value in the decoded bitmap for each opaque digit
+    pub background: Option<u8>, // Pixel value where every layer is transparent, which is otherwise an error
+}
+
+impl Default for Semantics {
+    // 0 is black, 1 is white and 2 is transparent
+    fn default() -> Self {
+        Semantics {transparent: 1 << 2, colors: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9], background: None}
+    }
+}
+
+impl Semantics {
+    // Build semantics from optional command line strings, e.g. "29" for the transparent digits,
+    // "10" to give digit 0 the pixel value 1 and digit 1 the value 0, and "0" for the background
+    pub fn parse(transparent: Option<&str>, colors: Option<&str>, background: Option<&str>) -> io::Result<Self> {
+        let mut semantics = Semantics::default();
+        let digits = |s: &str| s.trim().chars()
+            .map(|c| c.to_digit(10).map(|d| d as u8).ok_or_else(||
+                io::Error::new(io::ErrorKind::InvalidData, format!("Invalid digit {:?} in {:?}", c, s))))
+            .collect::<io::Result<Vec<u8>>>();
+        if let Some(transparent) = transparent {
+            semantics.transparent = digits(transparent)?.iter().fold(0, |mask, &d| mask | 1 << d);
+        }
+        if let Some(colors) = colors {
+            let colors = digits(colors)?;
+            if colors.len() > 10 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "More than 10 colors"));
+            }
+            semantics.colors[..colors.len()].copy_from_slice(&colors);
+        }
+        if let Some(background) = background {
+            semantics.background = Some(background.trim().parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid background {}", background)))?);
+        }
+        Ok(semantics)
+    }
+
+    pub fn is_transparent(&self, digit: u8) -> bool {
+        self.transparent & 1 << digit != 0
+    }
+}
+
+#[test]
+fn test_decode() {
+    let image = SpaceImage::parse("0222112222120000", 2, 2).unwrap();
+    assert_eq!(image.decode(&Semantics::default()).unwrap().cells, &[0, 1, 1, 0]);
+    assert!(SpaceImage::parse("2222", 2, 2).unwrap().decode(&Semantics::default()).is_err());
+
+    // Swap black and white, and let 0 show through to a background of 5
+    let semantics = Semantics::parse(Some("02"), Some("10"), Some("5")).unwrap();
+    assert_eq!(image.decode(&semantics).unwrap().cells, &[0, 0, 0, 5]);
+    assert!(Semantics::parse(Some("x"), None, None).is_err());
+    assert!(Semantics::parse(None, Some("01234567890"), None).is_err());
+    assert!(Semantics::parse(None, None, Some("256")).is_err());
+
+    // Split the message across a front layer that covers the left half and a back layer
+    let message = ocr::render("CFLUZ", Font::Small).unwrap();
+    let front = message.iter()
+        .map(|(p, &pixel)| if (p.x as usize) < message.width / 2 { pixel } else { 2 });
+    let back = message.cells.iter().cloned();
+    let input = front.chain(back).map(|d| char::from(b'0' + d)).collect::<String>();
+    let image = SpaceImage::parse(&input, message.width, message.height).unwrap();
+    assert_eq!(ocr::recognize(&image.decode(&Semantics::default()).unwrap(), 1).unwrap(), "CFLUZ");
+}
+
+#[derive(Clone, Debug)]
+pub struct EncodeOptions {
+    pub layers: usize, // Layers that may show each pixel, with the first opaque one picked at random
+    pub noise: usize, // Layers of random digits behind the rest, which never show
+    pub seed: u64,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {layers: 3, noise: 0, seed: 1}
+    }
+}
+
+impl SpaceImage {
+    // Layers that composite to target, from a xorshift generator
+    pub fn encode(target: &Bitmap, semantics: &Semantics, options: &EncodeOptions) -> io::Result<Self> {
+        if options.layers == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Need at least one layer"));
+        }
+        let mut rng = Xorshift::new(options.seed);
+        let mut next = move |n: usize| rng.below(n as u64) as usize;
+        let (transparent, opaque): (Vec<u8>, Vec<u8>) = (0..10).partition(|&d| semantics.is_transparent(d));
+
+        let count = options.layers + options.noise;
+        let mut layers = vec![Grid::new(target.width, target.height, 0); count];
+        for (p, &value) in target.iter() {
+            let digits = opaque.iter().cloned().filter(|&d| semantics.colors[d as usize] == value).collect::<Vec<u8>>();
+            // Without a transparent digit every pixel shows the front layer
+            let front = match (digits.is_empty(), transparent.is_empty()) {
+                (false, true) => 0,
+                (false, false) => next(options.layers),
+                (true, false) if semantics.background == Some(value) => count,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("No digit draws pixel value {} at ({}, {})", value, p.x, p.y))),
+            };
+            for (i, layer) in layers.iter_mut().enumerate() {
+                layer[p] = match i.cmp(&front) {
+                    Ordering::Less => transparent[next(transparent.len())],
+                    Ordering::Equal => digits[next(digits.len())],
+                    Ordering::Greater => next(10) as u8,
+                };
+            }
+        }
+        Ok(SpaceImage {width: target.width, height: target.height, layers})
+    }
+
+    pub fn to_digits(&self) -> String {
+        self.layers.iter().flat_map(|layer| layer.cells.iter()).map(|&d| char::from(b'0' + d)).collect()
+    }
+}
+
+#[test]
+fn test_encode() {
+    let message = ocr::render("CFLUZ", Font::Small).unwrap();
+    let options = EncodeOptions {layers: 4, noise: 2, seed: 7};
+    let image = SpaceImage::encode(&message, &Semantics::default(), &options).unwrap();
+    assert_eq!(image.layers.len(), 6);
+    let image = SpaceImage::parse(&image.to_digits(), message.width, message.height).unwrap();
+    assert_eq!(image.decode(&Semantics::default()).unwrap(), message);
+    assert_ne!(SpaceImage::encode(&message, &Semantics::default(), &EncodeOptions {seed: 8, ..options}).unwrap(), image);
+
+    // Digits 3 and 4 both draw pixel value 1, and pixel value 5 is left to the background
+    let target = Bitmap::from_cells(3, 2, vec![0, 1, 5, 1, 0, 5]).unwrap();
+    let semantics = Semantics {transparent: 0b1111100100, colors: [0, 1, 2, 1, 1, 5, 6, 7, 8, 9], background: Some(5)};
+    for seed in 0..20 {
+        let image = SpaceImage::encode(&target, &semantics, &EncodeOptions {layers: 3, noise: 1, seed}).unwrap();
+        assert_eq!(image.decode(&semantics).unwrap(), target);
+    }
+
+    // Nothing can be seen through, so only the front layer matters
+    let semantics = Semantics {transparent: 0, ..Semantics::default()};
+    let image = SpaceImage::encode(&target.map(|&p| p % 2), &semantics, &options).unwrap();
+    assert_eq!(image.layers[0], target.map(|&p| p % 2));
+
+    assert!(SpaceImage::encode(&target.map(|&p| p + 10), &Semantics::default(), &options).is_err());
+    assert!(SpaceImage::encode(&message, &Semantics::default(), &EncodeOptions {layers: 0, ..options}).is_err());
+}